@@ -1,3 +1,6 @@
+// Requests go to the same origin, through the dev server's /api proxy,
+// which adds the API key (see vite.config.js).
+
 /**
  * Searches for transactions by calling the external API
  * @param {string} query - The search query (address)
@@ -7,7 +10,7 @@ export async function searchTransactions(query) {
   if (!query) return { transactions: [], account: null };
 
   try {
-    let uri = `http://127.0.0.1:8080/api/transactions/${query}`;
+    let uri = `/api/transactions/${query}`;
     const response = await fetch(uri);
 
     // Check if the request was successful
@@ -19,7 +22,7 @@ export async function searchTransactions(query) {
     const data = await response.json();
     console.log("Data:", data);
 
-    const transactions = data || [];
+    const transactions = data.data || [];
 
     // Extract account information if available
     // Assuming the API might return account info along with transactions
@@ -44,7 +47,7 @@ export async function getWalletBalance(address) {
   try {
     // Call the balance API endpoint
     const response = await fetch(
-      `http://127.0.0.1:8080/api/wallet/balance/${encodeURIComponent(address)}`
+      `/api/wallet/balance/${encodeURIComponent(address)}?asset=ETH`
     );
 
     // Check if the request was successful
@@ -71,7 +74,7 @@ export async function getWalletBalance(address) {
  */
 export async function createTransaction(transactionData) {
   try {
-    const response = await fetch("http://127.0.0.1:8080/api/transactions", {
+    const response = await fetch("/api/transactions", {
       method: "POST",
       headers: {
         "Content-Type": "application/json",
@@ -80,9 +83,11 @@ export async function createTransaction(transactionData) {
     });
 
     if (!response.ok) {
-      const errorData = await response.json();
+      // Errors are RFC 7807 problem documents listing each problem found.
+      const problem = await response.json();
+      const details = (problem.errors || []).map((error) => error.detail);
       throw new Error(
-        errorData.message ||
+        (details.length ? details.join(",") : problem.detail) ||
           `API error: ${response.status} ${response.statusText}`
       );
     }
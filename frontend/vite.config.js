@@ -1,8 +1,23 @@
-import { defineConfig } from "vite";
+import { defineConfig, loadEnv } from "vite";
 import react from "@vitejs/plugin-react";
 import tailwindcss from "@tailwindcss/vite";
 
 // https://vite.dev/config/
-export default defineConfig({
-  plugins: [react(), tailwindcss()],
+export default defineConfig(({ mode }) => {
+  // API_KEY has no VITE_ prefix, so it stays in the dev server and is never
+  // compiled into the bundle. The proxy adds it to the requests it forwards.
+  const env = loadEnv(mode, process.cwd(), "");
+
+  return {
+    plugins: [react(), tailwindcss()],
+    server: {
+      proxy: {
+        "/api": {
+          target: env.API_URL || "http://127.0.0.1:8080",
+          changeOrigin: true,
+          headers: env.API_KEY ? { Authorization: `Bearer ${env.API_KEY}` } : {},
+        },
+      },
+    },
+  };
 });
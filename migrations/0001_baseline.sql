@@ -0,0 +1,120 @@
+-- Baseline schema, formerly applied by docker-compose from init-db/init.sql.
+-- Every statement is idempotent so databases created that way adopt it as is.
+CREATE SCHEMA IF NOT EXISTS cryptocurrency_transactions;
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.assets (
+    symbol VARCHAR(16) PRIMARY KEY,
+    decimals SMALLINT NOT NULL CHECK (decimals BETWEEN 0 AND 18),
+    contract_address VARCHAR(255) UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO cryptocurrency_transactions.assets (symbol, decimals, contract_address) VALUES
+    ('ETH', 18, NULL),
+    ('USDC', 6, '0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48'),
+    ('USDT', 6, '0xdac17f958d2ee523a2206206994597c13d831ec7'),
+    ('DAI', 18, '0x6b175474e89094c44da98b954eedeac495271d0f')
+ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.transactions (
+    id SERIAL PRIMARY KEY,
+    address_from VARCHAR(255) NOT NULL,
+    address_to VARCHAR(255) NOT NULL,
+    amount NUMERIC(38,18) NOT NULL,
+    asset VARCHAR(16) NOT NULL DEFAULT 'ETH' REFERENCES cryptocurrency_transactions.assets (symbol),
+    type VARCHAR(10) NOT NULL CHECK (type IN ('Deposit', 'Withdrawal')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    reversal_of INTEGER UNIQUE REFERENCES cryptocurrency_transactions.transactions (id),
+    reversed_by INTEGER UNIQUE REFERENCES cryptocurrency_transactions.transactions (id),
+    status VARCHAR(10) NOT NULL DEFAULT 'confirmed'
+        CHECK (status IN ('pending', 'confirmed', 'failed', 'cancelled'))
+);
+
+CREATE INDEX IF NOT EXISTS transactions_pending_address_from_idx
+    ON cryptocurrency_transactions.transactions (address_from)
+    WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS transactions_created_at_id_idx
+    ON cryptocurrency_transactions.transactions (created_at, id);
+
+CREATE INDEX IF NOT EXISTS transactions_address_from_created_at_id_idx
+    ON cryptocurrency_transactions.transactions (address_from, created_at, id);
+
+CREATE INDEX IF NOT EXISTS transactions_address_to_created_at_id_idx
+    ON cryptocurrency_transactions.transactions (address_to, created_at, id);
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.idempotency_keys (
+    key VARCHAR(255) PRIMARY KEY,
+    fingerprint CHAR(64) NOT NULL,
+    transaction_id INTEGER NOT NULL REFERENCES cryptocurrency_transactions.transactions (id),
+    status SMALLINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.accounts (
+    address VARCHAR(255) PRIMARY KEY,
+    overdraft_policy VARCHAR(10) NOT NULL DEFAULT 'strict'
+        CHECK (overdraft_policy IN ('strict', 'overdraft', 'unlimited')),
+    overdraft_limit NUMERIC(30,10) NOT NULL DEFAULT 0 CHECK (overdraft_limit >= 0),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.postings (
+    id BIGSERIAL PRIMARY KEY,
+    transaction_id INTEGER NOT NULL REFERENCES cryptocurrency_transactions.transactions (id),
+    account VARCHAR(255) NOT NULL REFERENCES cryptocurrency_transactions.accounts (address),
+    asset VARCHAR(16) NOT NULL REFERENCES cryptocurrency_transactions.assets (symbol),
+    amount NUMERIC(38,18) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS postings_transaction_id_idx
+    ON cryptocurrency_transactions.postings (transaction_id);
+
+CREATE INDEX IF NOT EXISTS postings_account_idx
+    ON cryptocurrency_transactions.postings (account);
+
+-- Backfill postings for transactions recorded before the ledger existed.
+INSERT INTO cryptocurrency_transactions.accounts (address)
+SELECT address_from FROM cryptocurrency_transactions.transactions
+UNION
+SELECT address_to FROM cryptocurrency_transactions.transactions
+ON CONFLICT DO NOTHING;
+
+INSERT INTO cryptocurrency_transactions.postings (transaction_id, account, asset, amount)
+SELECT t.id, entry.account, t.asset, entry.amount
+FROM cryptocurrency_transactions.transactions t
+CROSS JOIN LATERAL (VALUES (t.address_from, -t.amount), (t.address_to, t.amount))
+    AS entry (account, amount)
+WHERE t.status = 'confirmed' AND NOT EXISTS (
+    SELECT 1 FROM cryptocurrency_transactions.postings p WHERE p.transaction_id = t.id
+);
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.balances (
+    address VARCHAR(255) NOT NULL REFERENCES cryptocurrency_transactions.accounts (address),
+    asset VARCHAR(16) NOT NULL REFERENCES cryptocurrency_transactions.assets (symbol),
+    balance NUMERIC(38,18) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (address, asset)
+);
+
+INSERT INTO cryptocurrency_transactions.balances (address, asset, balance)
+SELECT account, asset, SUM(amount) FROM cryptocurrency_transactions.postings
+GROUP BY account, asset
+ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS cryptocurrency_transactions.holds (
+    id SERIAL PRIMARY KEY,
+    address VARCHAR(255) NOT NULL,
+    asset VARCHAR(16) NOT NULL DEFAULT 'ETH' REFERENCES cryptocurrency_transactions.assets (symbol),
+    amount NUMERIC(38,18) NOT NULL CHECK (amount > 0),
+    captured_amount NUMERIC(38,18) NOT NULL DEFAULT 0,
+    transaction_id INTEGER UNIQUE REFERENCES cryptocurrency_transactions.transactions (id),
+    status VARCHAR(10) NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'captured', 'released', 'expired')),
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS holds_active_address_idx
+    ON cryptocurrency_transactions.holds (address)
+    WHERE status = 'active';
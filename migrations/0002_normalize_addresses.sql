@@ -0,0 +1,48 @@
+-- Rewrites every stored address in its canonical, all-lowercase form.
+-- Addresses that only differed in case are merged: their balances are added
+-- up and the lowercase account, or else the oldest one, keeps its overdraft
+-- policy.
+
+LOCK TABLE cryptocurrency_transactions.balances IN SHARE ROW EXCLUSIVE MODE;
+
+INSERT INTO cryptocurrency_transactions.accounts
+    (address, overdraft_policy, overdraft_limit, created_at)
+SELECT DISTINCT ON (lower(address))
+       lower(address), overdraft_policy, overdraft_limit, created_at
+FROM cryptocurrency_transactions.accounts
+WHERE address <> lower(address)
+ORDER BY lower(address), created_at
+ON CONFLICT (address) DO NOTHING;
+
+UPDATE cryptocurrency_transactions.transactions
+SET address_from = lower(address_from)
+WHERE address_from <> lower(address_from);
+
+UPDATE cryptocurrency_transactions.transactions
+SET address_to = lower(address_to)
+WHERE address_to <> lower(address_to);
+
+UPDATE cryptocurrency_transactions.postings
+SET account = lower(account)
+WHERE account <> lower(account);
+
+UPDATE cryptocurrency_transactions.holds
+SET address = lower(address)
+WHERE address <> lower(address);
+
+UPDATE cryptocurrency_transactions.assets
+SET contract_address = lower(contract_address)
+WHERE contract_address <> lower(contract_address);
+
+INSERT INTO cryptocurrency_transactions.balances (address, asset, balance)
+SELECT lower(address), asset, SUM(balance)
+FROM cryptocurrency_transactions.balances
+WHERE address <> lower(address)
+GROUP BY lower(address), asset
+ON CONFLICT (address, asset) DO UPDATE
+SET balance = balances.balance + EXCLUDED.balance,
+    updated_at = NOW();
+
+DELETE FROM cryptocurrency_transactions.balances WHERE address <> lower(address);
+
+DELETE FROM cryptocurrency_transactions.accounts WHERE address <> lower(address);
@@ -0,0 +1,15 @@
+-- API keys are stored as the hex SHA-256 of the secret; only `prefix` is kept
+-- in the clear so that keys can be told apart.
+CREATE TABLE cryptocurrency_transactions.api_keys (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    prefix VARCHAR(16) NOT NULL,
+    key_hash CHAR(64) NOT NULL UNIQUE,
+    scopes VARCHAR(32)[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    rotated_at TIMESTAMP,
+    revoked_at TIMESTAMP
+);
+
+ALTER TABLE cryptocurrency_transactions.transactions
+    ADD COLUMN api_key_id INTEGER REFERENCES cryptocurrency_transactions.api_keys (id);
@@ -0,0 +1,15 @@
+-- Users own addresses through `accounts.user_id` and act through API keys
+-- bound to them. Keys without a user act for the service itself.
+CREATE TABLE cryptocurrency_transactions.users (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE cryptocurrency_transactions.api_keys
+    ADD COLUMN user_id INTEGER REFERENCES cryptocurrency_transactions.users (id);
+
+ALTER TABLE cryptocurrency_transactions.accounts
+    ADD COLUMN user_id INTEGER REFERENCES cryptocurrency_transactions.users (id);
+
+CREATE INDEX accounts_user_id_idx ON cryptocurrency_transactions.accounts (user_id);
@@ -0,0 +1,7 @@
+-- Signed transaction requests carry a per-address nonce. Each one can be
+-- used once, so a captured signature cannot be replayed.
+ALTER TABLE cryptocurrency_transactions.transactions ADD COLUMN nonce BIGINT;
+
+CREATE UNIQUE INDEX transactions_address_from_nonce_idx
+    ON cryptocurrency_transactions.transactions (address_from, nonce)
+    WHERE nonce IS NOT NULL;
@@ -0,0 +1,57 @@
+-- Partners subscribe to the deposits and withdrawals of an address. Events are
+-- written to the outbox in the same database transaction as the transaction
+-- itself, then fanned out into one delivery per matching subscription.
+CREATE TABLE cryptocurrency_transactions.webhook_subscriptions (
+    id SERIAL PRIMARY KEY,
+    url TEXT NOT NULL,
+    address VARCHAR(255) NOT NULL,
+    event_types VARCHAR(32)[] NOT NULL,
+    secret VARCHAR(128) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP
+);
+
+CREATE INDEX webhook_subscriptions_address_idx
+    ON cryptocurrency_transactions.webhook_subscriptions (address)
+    WHERE deleted_at IS NULL;
+
+CREATE TABLE cryptocurrency_transactions.webhook_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    transaction_id INTEGER NOT NULL REFERENCES cryptocurrency_transactions.transactions (id),
+    event_type VARCHAR(32) NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMP
+);
+
+CREATE INDEX webhook_outbox_unprocessed_idx
+    ON cryptocurrency_transactions.webhook_outbox (id)
+    WHERE processed_at IS NULL;
+
+CREATE TABLE cryptocurrency_transactions.webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    subscription_id INTEGER NOT NULL REFERENCES cryptocurrency_transactions.webhook_subscriptions (id),
+    outbox_id BIGINT NOT NULL REFERENCES cryptocurrency_transactions.webhook_outbox (id),
+    status VARCHAR(16) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMP,
+    UNIQUE (subscription_id, outbox_id)
+);
+
+CREATE INDEX webhook_deliveries_due_idx
+    ON cryptocurrency_transactions.webhook_deliveries (next_attempt_at)
+    WHERE status = 'pending';
+
+CREATE TABLE cryptocurrency_transactions.webhook_delivery_attempts (
+    id BIGSERIAL PRIMARY KEY,
+    delivery_id BIGINT NOT NULL REFERENCES cryptocurrency_transactions.webhook_deliveries (id),
+    attempted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    response_status SMALLINT,
+    error TEXT
+);
+
+CREATE INDEX webhook_delivery_attempts_delivery_id_idx
+    ON cryptocurrency_transactions.webhook_delivery_attempts (delivery_id);
@@ -0,0 +1,4 @@
+-- Pending transactions are settled by confirming, cancelling or failing them.
+-- The key that did so is kept alongside the key that created the transaction.
+ALTER TABLE cryptocurrency_transactions.transactions
+    ADD COLUMN settled_by_api_key_id INTEGER REFERENCES cryptocurrency_transactions.api_keys (id);
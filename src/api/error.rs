@@ -0,0 +1,286 @@
+use crate::modules::metrics::metrics;
+use actix_web::http::{StatusCode, header};
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use std::fmt;
+use tracing::error;
+
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Stable, machine-readable reason for a failed request. Clients should match
+/// on these rather than on the English `detail` text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    ValidationFailed,
+    MalformedRequest,
+    InvalidParameter,
+    InvalidAddress,
+    InvalidChecksum,
+    SameAddress,
+    InvalidAmount,
+    InvalidPrecision,
+    InsufficientBalance,
+    UnknownAsset,
+    InvalidStatus,
+    InvalidExpiry,
+    InvalidSymbol,
+    InvalidDecimals,
+    InvalidPolicy,
+    InvalidIdempotencyKey,
+    IdempotencyKeyReused,
+    TransactionNotFound,
+    HoldNotFound,
+    AssetNotFound,
+    AlreadyReversed,
+    NotReversible,
+    InvalidTransition,
+    HoldExpired,
+    HoldAlreadySettled,
+    AssetExists,
+    InvalidScopes,
+    Unauthenticated,
+    InvalidApiKey,
+    InsufficientScope,
+    ApiKeyNotFound,
+    ApiKeyRevoked,
+    UnknownUser,
+    UserNotFound,
+    AddressOwned,
+    NotAddressOwner,
+    AddressNotFound,
+    InvalidSignature,
+    SignatureRequired,
+    InvalidNonce,
+    InvalidUrl,
+    InvalidEventTypes,
+    WebhookNotFound,
+    DatabaseError,
+}
+
+/// One problem with a request, and the request field it concerns.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub code: ErrorCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<&'static str>,
+    pub detail: String,
+}
+
+impl FieldError {
+    pub fn new(code: ErrorCode, field: &'static str, detail: impl Into<String>) -> Self {
+        FieldError {
+            code,
+            field: Some(field),
+            detail: detail.into(),
+        }
+    }
+
+    /// A problem with the request as a whole rather than one of its fields.
+    pub fn general(code: ErrorCode, detail: impl Into<String>) -> Self {
+        FieldError {
+            code,
+            field: None,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Every way a handler can fail. Rendered as an RFC 7807 problem document.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request is invalid; every problem found is reported.
+    Validation(Vec<FieldError>),
+    /// No usable credentials were presented.
+    Unauthorized(FieldError),
+    /// The credentials do not allow the request.
+    Forbidden(FieldError),
+    /// The resource named in the path does not exist.
+    NotFound(FieldError),
+    /// The request conflicts with the current state of the resource.
+    Conflict(FieldError),
+    /// The request is well-formed but cannot be processed as sent.
+    Unprocessable(FieldError),
+    Database(sqlx::Error),
+}
+
+impl ApiError {
+    pub fn not_found(code: ErrorCode, detail: impl Into<String>) -> Self {
+        ApiError::NotFound(FieldError::general(code, detail))
+    }
+
+    pub fn conflict(code: ErrorCode, detail: impl Into<String>) -> Self {
+        ApiError::Conflict(FieldError::general(code, detail))
+    }
+
+    /// The problem document for this error. A validation failure with a
+    /// single problem is reported under that problem's code; with several,
+    /// under `VALIDATION_FAILED` and each one is listed in `errors`.
+    pub fn problem(&self) -> Problem {
+        let status = self.status_code();
+        let (error, errors) = match self {
+            ApiError::Validation(errors) => match errors.as_slice() {
+                [error] => (error.clone(), errors.clone()),
+                _ => (
+                    FieldError::general(
+                        ErrorCode::ValidationFailed,
+                        "The request failed validation.",
+                    ),
+                    errors.clone(),
+                ),
+            },
+            ApiError::Unauthorized(error)
+            | ApiError::Forbidden(error)
+            | ApiError::NotFound(error)
+            | ApiError::Conflict(error)
+            | ApiError::Unprocessable(error) => (error.clone(), vec![]),
+            ApiError::Database(_) => (
+                FieldError::general(ErrorCode::DatabaseError, "Database error occurred"),
+                vec![],
+            ),
+        };
+
+        Problem {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or_default(),
+            status: status.as_u16(),
+            code: error.code,
+            field: error.field,
+            detail: error.detail,
+            errors,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Database(err) => write!(f, "Database error: {}", err),
+            _ => write!(f, "{}", self.problem().detail),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        ApiError::Database(err)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ApiError::Database(err) => error!("Database error: {}", err),
+            ApiError::Validation(errors) => {
+                for error in errors {
+                    metrics().validation_failed(error.code);
+                }
+            }
+            _ => {}
+        }
+
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::Unauthorized(_) = self {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+
+        response
+            .content_type(PROBLEM_CONTENT_TYPE)
+            .json(self.problem())
+    }
+}
+
+/// RFC 7807 problem details, extended with `code`, `field` and `errors`.
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    pub code: ErrorCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<&'static str>,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// Reports a request whose body, query string or path could not be parsed.
+pub fn malformed_request(err: impl fmt::Display) -> actix_web::Error {
+    ApiError::Validation(vec![FieldError::general(
+        ErrorCode::MalformedRequest,
+        err.to_string(),
+    )])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+
+    #[test]
+    fn test_single_validation_error_is_promoted() {
+        let error = ApiError::Validation(vec![FieldError::new(
+            ErrorCode::InsufficientBalance,
+            "amount",
+            "Insufficient balance",
+        )]);
+
+        let problem = error.problem();
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.code, ErrorCode::InsufficientBalance);
+        assert_eq!(problem.field, Some("amount"));
+        assert_eq!(problem.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_several_validation_errors_are_listed() {
+        let error = ApiError::Validation(vec![
+            FieldError::new(ErrorCode::InvalidAddress, "address_from", "a"),
+            FieldError::new(ErrorCode::InvalidAmount, "amount", "b"),
+        ]);
+
+        let problem = error.problem();
+        assert_eq!(problem.code, ErrorCode::ValidationFailed);
+        assert_eq!(problem.field, None);
+        assert_eq!(problem.errors.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_errors_render_as_problem_json() {
+        let response =
+            ApiError::not_found(ErrorCode::HoldNotFound, "Hold not found.").error_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            PROBLEM_CONTENT_TYPE
+        );
+
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "code": "HOLD_NOT_FOUND",
+                "detail": "Hold not found.",
+            })
+        );
+    }
+}
@@ -1,15 +1,27 @@
+pub mod error;
 mod services;
 
+use crate::api::error::malformed_request;
 use crate::api::services::alive;
-use crate::configurations::load_config;
-use crate::modules::{transactions, wallet};
+use crate::configurations::{load_config, run_migrations};
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::middleware::RequireScopes;
+use crate::modules::events::{EventBus, bridge};
+use crate::modules::holds::sweeper;
+use crate::modules::metrics::middleware::RecordMetrics;
+use crate::modules::transactions::repository::{PgTransactionRepository, TransactionRepository};
+use crate::modules::webhooks::dispatcher;
+use crate::modules::{
+    api_keys, assets, events, holds, ledger, metrics, transactions, users, wallet, webhooks,
+};
 use actix_cors::Cors;
 use actix_web::dev::{Server, Service};
 use actix_web::http::StatusCode;
 use actix_web::{App, HttpResponse, HttpServer, web};
 use serde::Serialize;
-use sqlx::postgres::PgPoolOptions;
 use std::io::ErrorKind;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info};
 
 pub async fn start_api() -> std::io::Result<()> {
@@ -24,11 +36,7 @@ pub async fn start_api() -> std::io::Result<()> {
         }
     };
 
-    let pool = match PgPoolOptions::new()
-        .max_connections(config.db.max_connections)
-        .connect(&config.db.url)
-        .await
-    {
+    let pool = match config.db.connect().await {
         Ok(pool) => {
             println!("Successfully connected to database");
             web::Data::new(pool)
@@ -42,30 +50,50 @@ pub async fn start_api() -> std::io::Result<()> {
         }
     };
 
+    if config.db.migrate_on_startup
+        && let Err(e) = run_migrations(pool.get_ref()).await
+    {
+        error!("Failed to run migrations: {}", e);
+        return Err(std::io::Error::other("Failed to run migrations"));
+    }
+
+    actix_web::rt::spawn(sweeper::run(
+        pool.get_ref().clone(),
+        Duration::from_secs(config.holds.sweep_interval_seconds),
+    ));
+
+    actix_web::rt::spawn(dispatcher::run(
+        pool.get_ref().clone(),
+        config.webhooks.clone(),
+    ));
+
+    let repository: Arc<dyn TransactionRepository> =
+        Arc::new(PgTransactionRepository::new(pool.get_ref().clone()));
+    let repository = web::Data::from(repository);
+
+    // One bus for every worker, bridged to the other instances.
+    let events = Arc::new(EventBus::with_bridge(pool.get_ref().clone()));
+    actix_web::rt::spawn(bridge::run(pool.get_ref().clone(), events.clone()));
+    let events = web::Data::from(events);
+
     let config_data = web::Data::new(config);
     let api_bind = config_data.api.bind.clone();
     let api_workers = config_data.api.workers;
 
+    let allowed_origins = config_data.api.allowed_origins.clone();
+
     let http_server = HttpServer::new(move || {
         App::new()
-            .wrap(
-                Cors::default()
-                    .allow_any_origin()
-                    .allow_any_method()
-                    .allow_any_header(),
-            )
+            .wrap(cors(&allowed_origins))
+            .wrap(RecordMetrics)
+            .app_data(web::JsonConfig::default().error_handler(|err, _| malformed_request(err)))
+            .app_data(web::QueryConfig::default().error_handler(|err, _| malformed_request(err)))
+            .app_data(web::PathConfig::default().error_handler(|err, _| malformed_request(err)))
             .app_data(config_data.clone())
             .app_data(pool.clone())
-            .service(
-                web::scope("/api")
-                    .service(
-                        web::scope("/alive")
-                            .service(alive)
-                            .wrap_fn(|s, r| r.call(s)),
-                    )
-                    .service(web::scope("/transactions").configure(transactions::api_config))
-                    .service(web::scope("/wallet").configure(wallet::api_config)),
-            )
+            .app_data(repository.clone())
+            .app_data(events.clone())
+            .configure(api_routes)
     })
     .workers(api_workers);
 
@@ -74,14 +102,87 @@ pub async fn start_api() -> std::io::Result<()> {
     server.await
 }
 
+/// Every endpoint under `/api`, and `/metrics` for Prometheus. All but
+/// `/alive` and `/metrics` require an API key with the read scope for GET
+/// requests and the write scope for anything else.
+pub fn api_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/metrics").configure(metrics::api_config));
+    cfg.service(
+        web::scope("/api")
+            .service(
+                web::scope("/alive")
+                    .service(alive)
+                    .wrap_fn(|s, r| r.call(s)),
+            )
+            .service(
+                web::scope("/transactions/import")
+                    .wrap(RequireScopes::new(Scope::Admin, Scope::Admin))
+                    .configure(transactions::import_config),
+            )
+            .service(
+                web::scope("/transactions")
+                    .wrap(RequireScopes::new(
+                        Scope::ReadTransactions,
+                        Scope::WriteTransactions,
+                    ))
+                    .configure(events::api_config)
+                    .configure(transactions::api_config),
+            )
+            .service(
+                web::scope("/wallet")
+                    .wrap(RequireScopes::new(Scope::ReadBalance, Scope::Admin))
+                    .configure(wallet::api_config),
+            )
+            .service(
+                web::scope("/holds")
+                    .wrap(RequireScopes::new(
+                        Scope::ReadTransactions,
+                        Scope::WriteTransactions,
+                    ))
+                    .configure(holds::api_config),
+            )
+            .service(
+                web::scope("/assets")
+                    .wrap(RequireScopes::new(Scope::ReadBalance, Scope::Admin))
+                    .configure(assets::api_config),
+            )
+            .service(
+                web::scope("/ledger")
+                    .wrap(RequireScopes::new(Scope::Admin, Scope::Admin))
+                    .configure(ledger::api_config)
+                    .configure(wallet::ledger_config),
+            )
+            .service(
+                web::scope("/keys")
+                    .wrap(RequireScopes::new(Scope::Admin, Scope::Admin))
+                    .configure(api_keys::api_config),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(RequireScopes::new(Scope::Admin, Scope::Admin))
+                    .configure(users::api_config),
+            )
+            .service(
+                web::scope("/webhooks")
+                    .wrap(RequireScopes::new(Scope::Admin, Scope::Admin))
+                    .configure(webhooks::api_config),
+            ),
+    );
+}
+
+/// Allows `allowed_origins` only, so cross-origin requests are refused
+/// when none are configured.
+fn cors(allowed_origins: &[String]) -> Cors {
+    allowed_origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allow_any_method()
+        .allow_any_header()
+}
+
 pub fn build_json_response<T>(response: T, status_code: StatusCode) -> HttpResponse
 where
     T: Serialize,
 {
     HttpResponse::build(status_code).json(response)
 }
-
-#[derive(Serialize)]
-pub struct ErrorResponse {
-    pub message: Vec<String>,
-}
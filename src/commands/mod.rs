@@ -0,0 +1,137 @@
+use crate::configurations::{MIGRATOR, load_config, run_migrations};
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::services::create_api_key;
+use crate::modules::transactions::import::import_transactions;
+use crate::modules::wallet::repository::rebuild_balances;
+use crate::modules::wallet::services::verify_stored_balances;
+use sqlx::PgPool;
+
+/// Applies any schema migrations the database has not seen yet.
+pub async fn migrate_command() -> std::io::Result<()> {
+    let pool = connect().await?;
+
+    run_migrations(&pool)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Failed to run migrations: {}", e)))?;
+
+    let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap_or_default();
+    println!("Database schema is at version {}", latest);
+    Ok(())
+}
+
+/// Issues an API key named `name` with `scopes` and prints its secret, which
+/// cannot be retrieved later. This is how the first admin key is created.
+pub async fn issue_key_command(name: &str, scopes: &[String]) -> std::io::Result<()> {
+    let scopes = scopes
+        .iter()
+        .map(|scope| scope.parse::<Scope>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(std::io::Error::other)?;
+    if name.trim().is_empty() || scopes.is_empty() {
+        return Err(std::io::Error::other(
+            "An API key needs a name and at least one scope",
+        ));
+    }
+
+    let pool = connect().await?;
+
+    let issued = create_api_key(&pool, name.trim(), &scopes, None)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Failed to issue API key: {}", e)))?;
+
+    println!(
+        "Issued API key {} ({})",
+        issued.api_key.id, issued.api_key.name
+    );
+    println!("{}", issued.key);
+    Ok(())
+}
+
+/// Recomputes the materialized `balances` table from `transactions`.
+pub async fn rebuild_balances_command() -> std::io::Result<()> {
+    let pool = connect().await?;
+
+    match rebuild_balances(&pool).await {
+        Ok(rebuilt) => {
+            println!("Rebuilt balances for {} addresses", rebuilt);
+            Ok(())
+        }
+        Err(e) => Err(std::io::Error::other(format!(
+            "Failed to rebuild balances: {}",
+            e
+        ))),
+    }
+}
+
+/// Compares up to `sample` materialized balances against transaction history.
+pub async fn check_balances_command(sample: i64) -> std::io::Result<()> {
+    let pool = connect().await?;
+
+    let check = verify_stored_balances(&pool, sample)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Failed to check balances: {}", e)))?;
+
+    for mismatch in &check.mismatches {
+        println!(
+            "{} {}: stored {} computed {}",
+            mismatch.address, mismatch.asset, mismatch.stored, mismatch.computed
+        );
+    }
+    println!(
+        "Checked {} balances, {} mismatches",
+        check.checked,
+        check.mismatches.len()
+    );
+
+    if check.mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other("Stored balances are inconsistent"))
+    }
+}
+
+/// Imports the historical transactions in the CSV file at `path`, or with
+/// `dry_run` only reports what is wrong with it. Nothing is written unless
+/// every row is valid.
+pub async fn import_command(path: &str, dry_run: bool) -> std::io::Result<()> {
+    let csv = std::fs::read(path)?;
+    let pool = connect().await?;
+
+    let report = import_transactions(&pool, &csv, dry_run)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Failed to import {}: {}", path, e)))?;
+
+    for row in &report.errors {
+        for error in &row.errors {
+            match error.field {
+                Some(field) => println!("line {}: {}: {}", row.line, field, error.detail),
+                None => println!("line {}: {}", row.line, error.detail),
+            }
+        }
+    }
+
+    if !report.errors.is_empty() {
+        Err(std::io::Error::other(format!(
+            "{} of {} rows are invalid, nothing was imported",
+            report.errors.len(),
+            report.rows
+        )))
+    } else if report.committed {
+        println!("Imported {} transactions", report.rows);
+        Ok(())
+    } else {
+        println!("All {} rows are valid (dry run)", report.rows);
+        Ok(())
+    }
+}
+
+async fn connect() -> std::io::Result<PgPool> {
+    let config = load_config()
+        .map_err(|e| std::io::Error::other(format!("Failed to load config: {}", e)))?;
+
+    config
+        .db
+        .connect()
+        .await
+        .map_err(|e| std::io::Error::other(format!("Failed to connect to database: {}", e)))
+}
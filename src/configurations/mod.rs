@@ -1,4 +1,7 @@
 use serde::Deserialize;
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Executor, PgPool};
 use std::fs::File;
 use std::io::Read;
 
@@ -6,18 +9,101 @@ use std::io::Read;
 pub struct Config {
     pub api: Api,
     pub db: Db,
+    pub idempotency: Idempotency,
+    pub holds: Holds,
+    #[serde(default)]
+    pub signatures: Signatures,
+    #[serde(default)]
+    pub webhooks: Webhooks,
 }
 
 #[derive(Clone, Deserialize)]
 pub struct Api {
     pub bind: String,
     pub workers: usize,
+    /// Origins allowed by CORS. Any origin is allowed when empty.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
 }
 
 #[derive(Clone, Deserialize)]
 pub struct Db {
     pub url: String,
     pub max_connections: u32,
+    #[serde(default = "default_migrate_on_startup")]
+    pub migrate_on_startup: bool,
+}
+
+fn default_migrate_on_startup() -> bool {
+    true
+}
+
+/// Schema migrations in `migrations/`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+pub const SCHEMA: &str = "cryptocurrency_transactions";
+
+impl Db {
+    pub async fn connect(&self) -> Result<PgPool, sqlx::Error> {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .after_connect(|conn, _meta| {
+                Box::pin(async move {
+                    conn.execute(format!("SET search_path TO {}, public", SCHEMA).as_str())
+                        .await?;
+                    Ok(())
+                })
+            })
+            .connect(&self.url)
+            .await
+    }
+}
+
+/// Applies pending migrations, recording them in `_sqlx_migrations` inside
+/// the application schema.
+pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
+    pool.execute(format!("CREATE SCHEMA IF NOT EXISTS {}", SCHEMA).as_str())
+        .await?;
+    MIGRATOR.run(pool).await
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Idempotency {
+    pub ttl_seconds: i64,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Holds {
+    pub sweep_interval_seconds: u64,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct Signatures {
+    /// Reject transactions that are not signed by their source address.
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Webhooks {
+    pub poll_interval_seconds: u64,
+    /// Attempts made at a delivery before it is marked failed.
+    pub max_attempts: i32,
+    /// Delay before the first retry; each further retry waits twice as long.
+    pub backoff_base_seconds: i64,
+    pub timeout_seconds: u64,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Webhooks {
+            poll_interval_seconds: 5,
+            max_attempts: 8,
+            backoff_base_seconds: 10,
+            timeout_seconds: 10,
+        }
+    }
 }
 
 pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
@@ -1,12 +1,46 @@
 use crate::api::start_api;
+use crate::commands::{
+    check_balances_command, import_command, issue_key_command, migrate_command,
+    rebuild_balances_command,
+};
+use crate::modules::wallet::services::DEFAULT_BALANCE_CHECK_SAMPLE;
 
 mod api;
+mod commands;
 mod configurations;
 mod modules;
 
+const USAGE: &str = "Usage: r-r-challengue [serve | migrate | rebuild-balances | check-balances [SAMPLE] | issue-key NAME SCOPE... | import FILE [--dry-run]]";
+
 #[actix_rt::main]
 async fn main() {
-    if let Err(e) = start_api().await {
-        eprintln!("Failed to start API: {}", e);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        None | Some("serve") => start_api()
+            .await
+            .map_err(|e| std::io::Error::other(format!("Failed to start API: {}", e))),
+        Some("migrate") => migrate_command().await,
+        Some("rebuild-balances") => rebuild_balances_command().await,
+        Some("check-balances") => match args.get(1).map(|sample| sample.parse()) {
+            None => check_balances_command(DEFAULT_BALANCE_CHECK_SAMPLE).await,
+            Some(Ok(sample)) => check_balances_command(sample).await,
+            Some(Err(_)) => Err(std::io::Error::other(USAGE)),
+        },
+        Some("issue-key") => match args.get(1) {
+            Some(name) => issue_key_command(name, &args[2..]).await,
+            None => Err(std::io::Error::other(USAGE)),
+        },
+        Some("import") => match (args.get(1), args.get(2).map(String::as_str)) {
+            (Some(path), None) => import_command(path, false).await,
+            (Some(path), Some("--dry-run")) => import_command(path, true).await,
+            _ => Err(std::io::Error::other(USAGE)),
+        },
+        Some(_) => Err(std::io::Error::other(USAGE)),
     };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
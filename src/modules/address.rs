@@ -0,0 +1,137 @@
+use crate::api::error::{ErrorCode, FieldError};
+use sha3::{Digest, Keccak256};
+
+/// Why a string is not an acceptable Ethereum address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressError {
+    /// Not `0x` followed by 40 hex digits.
+    Format,
+    /// Mixed case that does not match the EIP-55 checksum.
+    Checksum,
+}
+
+impl AddressError {
+    /// Reports the error against request field `field`, described to the
+    /// client as `label`.
+    pub fn for_field(self, field: &'static str, label: &str) -> FieldError {
+        match self {
+            AddressError::Format => FieldError::new(
+                ErrorCode::InvalidAddress,
+                field,
+                format!("Invalid {} format.", label),
+            ),
+            AddressError::Checksum => FieldError::new(
+                ErrorCode::InvalidChecksum,
+                field,
+                format!("Invalid {} checksum.", label),
+            ),
+        }
+    }
+}
+
+/// Validates `address` and returns its canonical, all-lowercase form.
+///
+/// All-lowercase and all-uppercase addresses carry no checksum and are
+/// accepted as is; mixed-case addresses must match their EIP-55 checksum.
+pub fn normalize(address: &str) -> Result<String, AddressError> {
+    let digits = address
+        .strip_prefix("0x")
+        .filter(|digits| digits.len() == 40 && digits.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or(AddressError::Format)?;
+
+    let lowercase = digits.to_ascii_lowercase();
+    if digits != lowercase
+        && digits != digits.to_ascii_uppercase()
+        && digits != checksum_digits(&lowercase)
+    {
+        return Err(AddressError::Checksum);
+    }
+
+    Ok(format!("0x{}", lowercase))
+}
+
+/// Canonical form used to look addresses up. Unlike [`normalize`] it accepts
+/// any string, so lookups of malformed addresses simply find nothing.
+pub fn canonical(address: &str) -> String {
+    address.to_ascii_lowercase()
+}
+
+/// Uppercases each letter whose nibble in the Keccak-256 hash of the
+/// lowercase digits is 8 or more.
+fn checksum_digits(lowercase: &str) -> String {
+    let hash = Keccak256::digest(lowercase.as_bytes());
+
+    lowercase
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from EIP-55.
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn test_checksum_matches_eip55_vectors() {
+        for address in CHECKSUMMED {
+            assert_eq!(
+                format!("0x{}", checksum_digits(&address[2..].to_lowercase())),
+                address
+            );
+            assert_eq!(normalize(address).unwrap(), address.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_single_case_addresses_skip_the_checksum() {
+        let address = "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED";
+        assert_eq!(normalize(address).unwrap(), address.to_lowercase());
+        assert_eq!(
+            normalize(&address.to_lowercase()).unwrap(),
+            address.to_lowercase()
+        );
+    }
+
+    #[test]
+    fn test_wrong_checksum_is_rejected() {
+        // Last letter's case flipped.
+        assert_eq!(
+            normalize("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            Err(AddressError::Checksum)
+        );
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        for address in [
+            "",
+            "0x",
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAe",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAedd",
+            "0xZaAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        ] {
+            assert_eq!(normalize(address), Err(AddressError::Format), "{}", address);
+        }
+    }
+}
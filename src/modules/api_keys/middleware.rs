@@ -0,0 +1,121 @@
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::modules::api_keys::repository::find_active_api_key;
+use crate::modules::api_keys::response::ApiKey;
+use crate::modules::api_keys::{Scope, hash_secret};
+use actix_web::body::EitherBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::{Method, header};
+use actix_web::{Error, HttpMessage, web};
+use sqlx::PgPool;
+use std::future::{Future, Ready, ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// Middleware that authenticates the `Authorization: Bearer <key>` header and
+/// requires the key to carry `read` for GET and HEAD requests and `write` for
+/// every other method. Handlers receive the key as `web::ReqData<ApiKey>`.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireScopes {
+    read: Scope,
+    write: Scope,
+}
+
+impl RequireScopes {
+    pub fn new(read: Scope, write: Scope) -> Self {
+        RequireScopes { read, write }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScopes
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireScopesMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireScopesMiddleware {
+            service: Rc::new(service),
+            scopes: *self,
+        }))
+    }
+}
+
+pub struct RequireScopesMiddleware<S> {
+    service: Rc<S>,
+    scopes: RequireScopes,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireScopesMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let required = match *req.method() {
+            Method::GET | Method::HEAD => self.scopes.read,
+            _ => self.scopes.write,
+        };
+
+        Box::pin(async move {
+            match authenticate(&req, required).await {
+                Ok(api_key) => {
+                    req.extensions_mut().insert(api_key);
+                    let response = service.call(req).await?;
+                    Ok(response.map_into_left_body())
+                }
+                Err(err) => Ok(req.error_response(err).map_into_right_body()),
+            }
+        })
+    }
+}
+
+/// The key presented by `req`, provided it is active and grants `required`.
+async fn authenticate(req: &ServiceRequest, required: Scope) -> Result<ApiKey, Error> {
+    let secret = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| {
+            ApiError::Unauthorized(FieldError::general(
+                ErrorCode::Unauthenticated,
+                "An API key is required as a Bearer token.",
+            ))
+        })?;
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("Database pool not found"))?;
+
+    let api_key = find_active_api_key(pool, &hash_secret(secret.trim()))
+        .await
+        .map_err(ApiError::from)?
+        .ok_or_else(|| {
+            ApiError::Unauthorized(FieldError::general(
+                ErrorCode::InvalidApiKey,
+                "The API key is invalid or has been revoked.",
+            ))
+        })?;
+
+    if !api_key.allows(required) {
+        return Err(ApiError::Forbidden(FieldError::general(
+            ErrorCode::InsufficientScope,
+            format!("The API key lacks the {} scope.", required),
+        ))
+        .into());
+    }
+
+    Ok(api_key)
+}
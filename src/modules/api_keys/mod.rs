@@ -0,0 +1,131 @@
+use crate::modules::api_keys::services::{issue_key, list_keys, revoke_key, rotate_key};
+use actix_web::web;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+pub mod middleware;
+pub mod repository;
+mod request;
+pub mod response;
+pub mod services;
+
+#[cfg(test)]
+pub mod test;
+
+/// Every secret starts with this, so leaked keys are easy to recognize.
+pub const SECRET_PREFIX: &str = "rr_";
+
+/// Characters of the secret kept in the clear to identify the key.
+const VISIBLE_SECRET_LENGTH: usize = SECRET_PREFIX.len() + 8;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_keys)
+        .service(issue_key)
+        .service(rotate_key)
+        .service(revoke_key);
+}
+
+/// What an API key may do. `admin` grants every other scope as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR")]
+pub enum Scope {
+    #[serde(rename = "read:transactions")]
+    #[sqlx(rename = "read:transactions")]
+    ReadTransactions,
+    #[serde(rename = "write:transactions")]
+    #[sqlx(rename = "write:transactions")]
+    WriteTransactions,
+    #[serde(rename = "read:balance")]
+    #[sqlx(rename = "read:balance")]
+    ReadBalance,
+    #[serde(rename = "admin")]
+    #[sqlx(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [
+        Scope::ReadTransactions,
+        Scope::WriteTransactions,
+        Scope::ReadBalance,
+        Scope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadTransactions => "read:transactions",
+            Scope::WriteTransactions => "write:transactions",
+            Scope::ReadBalance => "read:balance",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == name)
+            .ok_or_else(|| format!("Unknown scope: {}", name))
+    }
+}
+
+/// A new random secret. Only its hash and first few characters are stored.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", SECRET_PREFIX, hex::encode(bytes))
+}
+
+/// Hex-encoded SHA-256 of `secret`, as stored in `api_keys.key_hash`.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// The part of `secret` shown when listing keys.
+pub fn visible_prefix(secret: &str) -> &str {
+    &secret[..VISIBLE_SECRET_LENGTH.min(secret.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_unique_and_prefixed() {
+        let first = generate_secret();
+        let second = generate_secret();
+
+        assert_ne!(first, second);
+        assert!(first.starts_with(SECRET_PREFIX));
+        assert_eq!(first.len(), SECRET_PREFIX.len() + 64);
+        assert_eq!(visible_prefix(&first).len(), VISIBLE_SECRET_LENGTH);
+    }
+
+    #[test]
+    fn test_scopes_round_trip_through_their_names() {
+        for scope in Scope::ALL {
+            assert_eq!(scope.as_str().parse::<Scope>(), Ok(scope));
+        }
+        assert!("write:balance".parse::<Scope>().is_err());
+    }
+
+    #[test]
+    fn test_hash_is_stable_hex() {
+        assert_eq!(hash_secret("rr_secret"), hash_secret("rr_secret"));
+        assert_ne!(hash_secret("rr_secret"), hash_secret("rr_other"));
+        assert_eq!(hash_secret("rr_secret").len(), 64);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::response::ApiKey;
+use sqlx::PgPool;
+
+const API_KEY_COLUMNS: &str =
+    "id, name, prefix, scopes, user_id, created_at, rotated_at, revoked_at";
+
+pub(crate) async fn insert_api_key(
+    pool: &PgPool,
+    name: &str,
+    prefix: &str,
+    key_hash: &str,
+    scopes: &[Scope],
+    user_id: Option<i32>,
+) -> Result<ApiKey, sqlx::Error> {
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "INSERT INTO api_keys (name, prefix, key_hash, scopes, user_id)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING {}",
+        API_KEY_COLUMNS
+    ))
+    .bind(name)
+    .bind(prefix)
+    .bind(key_hash)
+    .bind(scopes)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+pub(crate) async fn get_api_keys(pool: &PgPool) -> Result<Vec<ApiKey>, sqlx::Error> {
+    let api_keys = sqlx::query_as::<_, ApiKey>(&format!(
+        "SELECT {} FROM api_keys ORDER BY id",
+        API_KEY_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(api_keys)
+}
+
+pub(crate) async fn get_api_key(pool: &PgPool, id: i32) -> Result<Option<ApiKey>, sqlx::Error> {
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "SELECT {} FROM api_keys WHERE id = $1",
+        API_KEY_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+/// The unrevoked key whose secret hashes to `key_hash`.
+pub(crate) async fn find_active_api_key(
+    pool: &PgPool,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "SELECT {} FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+        API_KEY_COLUMNS
+    ))
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+/// Replaces the secret of unrevoked key `id`. Returns `None` if there is no
+/// such key.
+pub(crate) async fn set_api_key_secret(
+    pool: &PgPool,
+    id: i32,
+    prefix: &str,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys SET prefix = $2, key_hash = $3, rotated_at = NOW()
+         WHERE id = $1 AND revoked_at IS NULL
+         RETURNING {}",
+        API_KEY_COLUMNS
+    ))
+    .bind(id)
+    .bind(prefix)
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+/// Revokes key `id`, keeping the original revocation time if it already was.
+pub(crate) async fn set_api_key_revoked(
+    pool: &PgPool,
+    id: i32,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys SET revoked_at = COALESCE(revoked_at, NOW())
+         WHERE id = $1
+         RETURNING {}",
+        API_KEY_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key)
+}
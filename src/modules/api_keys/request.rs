@@ -0,0 +1,33 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::api_keys::Scope;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct IssueApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub user_id: Option<i32>,
+}
+
+impl IssueApiKeyRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut result = vec![];
+
+        if self.name.trim().is_empty() || self.name.len() > 255 {
+            result.push(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "name",
+                "Name must be between 1 and 255 characters.",
+            ));
+        }
+        if self.scopes.is_empty() {
+            result.push(FieldError::new(
+                ErrorCode::InvalidScopes,
+                "scopes",
+                "At least one scope is required.",
+            ));
+        }
+
+        result
+    }
+}
@@ -0,0 +1,62 @@
+use crate::modules::api_keys::Scope;
+use crate::modules::holds::response::serialize_utc;
+use serde::{Serialize, Serializer};
+use sqlx::FromRow;
+use time::PrimitiveDateTime;
+
+/// An API key as listed to administrators. The secret itself is never stored.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    /// The user the key acts for. Keys without one act for the service.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    #[serde(serialize_with = "serialize_utc")]
+    pub created_at: PrimitiveDateTime,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_utc"
+    )]
+    pub rotated_at: Option<PrimitiveDateTime>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_utc"
+    )]
+    pub revoked_at: Option<PrimitiveDateTime>,
+}
+
+impl ApiKey {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+
+    /// Whether the key may move funds out of an address owned by `owner`:
+    /// its user must own the address, unless the key is an admin key.
+    pub fn may_spend_from(&self, owner: Option<i32>) -> bool {
+        self.scopes.contains(&Scope::Admin) || (self.user_id.is_some() && self.user_id == owner)
+    }
+}
+
+/// Response to issuing or rotating a key: the only time `key` is returned.
+#[derive(Debug, Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+fn serialize_optional_utc<S>(
+    date: &Option<PrimitiveDateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => serialize_utc(date, serializer),
+        None => serializer.serialize_none(),
+    }
+}
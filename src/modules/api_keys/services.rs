@@ -0,0 +1,112 @@
+use crate::api::build_json_response;
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::modules::api_keys::repository::{
+    get_api_key, get_api_keys, insert_api_key, set_api_key_revoked, set_api_key_secret,
+};
+use crate::modules::api_keys::request::IssueApiKeyRequest;
+use crate::modules::api_keys::response::IssuedApiKey;
+use crate::modules::api_keys::{Scope, generate_secret, hash_secret, visible_prefix};
+use crate::modules::users::repository::get_user;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, get, post, web};
+use sqlx::PgPool;
+
+#[get("")]
+async fn list_keys(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let api_keys = get_api_keys(&pool).await?;
+    Ok(build_json_response(api_keys, StatusCode::OK))
+}
+
+#[post("")]
+async fn issue_key(
+    pool: web::Data<PgPool>,
+    body: web::Json<IssueApiKeyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let request = body.into_inner();
+
+    let mut errors = request.validate();
+    if let Some(user_id) = request.user_id
+        && get_user(&pool, user_id).await?.is_none()
+    {
+        errors.push(FieldError::new(
+            ErrorCode::UnknownUser,
+            "user_id",
+            "Unknown user.",
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let issued =
+        create_api_key(&pool, request.name.trim(), &request.scopes, request.user_id).await?;
+    Ok(build_json_response(issued, StatusCode::CREATED))
+}
+
+/// Replaces the secret of a key. The old secret stops working immediately;
+/// the id, and with it the transactions recorded against the key, stay.
+#[post("{id}/rotate")]
+async fn rotate_key(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    let secret = generate_secret();
+    match set_api_key_secret(&pool, id, visible_prefix(&secret), &hash_secret(&secret)).await? {
+        Some(api_key) => Ok(build_json_response(
+            IssuedApiKey {
+                api_key,
+                key: secret,
+            },
+            StatusCode::OK,
+        )),
+        None => match get_api_key(&pool, id).await? {
+            Some(_) => Err(ApiError::conflict(
+                ErrorCode::ApiKeyRevoked,
+                "A revoked API key cannot be rotated.",
+            )),
+            None => Err(api_key_not_found()),
+        },
+    }
+}
+
+#[post("{id}/revoke")]
+async fn revoke_key(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let api_key = set_api_key_revoked(&pool, path.into_inner())
+        .await?
+        .ok_or_else(api_key_not_found)?;
+
+    Ok(build_json_response(api_key, StatusCode::OK))
+}
+
+/// Stores a new key with `scopes`, acting for `user_id`, and returns it with its secret.
+pub async fn create_api_key(
+    pool: &PgPool,
+    name: &str,
+    scopes: &[Scope],
+    user_id: Option<i32>,
+) -> Result<IssuedApiKey, sqlx::Error> {
+    let secret = generate_secret();
+    let api_key = insert_api_key(
+        pool,
+        name,
+        visible_prefix(&secret),
+        &hash_secret(&secret),
+        scopes,
+        user_id,
+    )
+    .await?;
+
+    Ok(IssuedApiKey {
+        api_key,
+        key: secret,
+    })
+}
+
+fn api_key_not_found() -> ApiError {
+    ApiError::not_found(ErrorCode::ApiKeyNotFound, "API key not found.")
+}
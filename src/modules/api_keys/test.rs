@@ -0,0 +1,200 @@
+use crate::api::api_routes;
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::services::create_api_key;
+use crate::modules::events::EventBus;
+use crate::modules::transactions::test::{
+    issuer, pg_repository, test_config, test_pool, unique_address,
+};
+use crate::modules::users::repository::{insert_user, set_address_owner};
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{App, test, web};
+use serde_json::json;
+
+fn bearer(secret: &str) -> (header::HeaderName, String) {
+    (header::AUTHORIZATION, format!("Bearer {}", secret))
+}
+
+#[actix_web::test]
+async fn test_endpoints_require_a_key_with_the_right_scope() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 33).await;
+    let address = unique_address(34);
+
+    let reader = create_api_key(&pool, "reader", &[Scope::ReadTransactions], None)
+        .await
+        .unwrap();
+    // Only the owner of an address may spend from it, deposits included.
+    let owner = insert_user(&pool, "issuer").await.unwrap();
+    assert!(set_address_owner(&pool, owner.id, &funder).await.unwrap());
+    let writer = create_api_key(&pool, "writer", &[Scope::WriteTransactions], Some(owner.id))
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::get().uri("/api/alive").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let deposit = || {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": funder,
+                "address_to": address,
+                "amount": "10",
+                "transaction_type": "Deposit",
+            }))
+    };
+
+    let response = test::call_service(&app, deposit().to_request()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        "Bearer"
+    );
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "UNAUTHENTICATED");
+
+    let request = deposit()
+        .insert_header(bearer("rr_not-a-real-key"))
+        .to_request();
+    let problem: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(problem["code"], "INVALID_API_KEY");
+
+    let request = deposit().insert_header(bearer(&reader.key)).to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "INSUFFICIENT_SCOPE");
+
+    let request = deposit().insert_header(bearer(&writer.key)).to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // The reader can list what the writer created, and who created it.
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/transactions/{}", address))
+        .insert_header(bearer(&reader.key))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(page["data"][0]["api_key_id"], writer.api_key.id);
+
+    // Neither key reaches the balance endpoints.
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}", address))
+        .insert_header(bearer(&writer.key))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_admins_issue_rotate_and_revoke_keys() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/keys")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "name": "reports", "scopes": ["read:balance"] }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let issued: serde_json::Value = test::read_body_json(response).await;
+    let id = issued["id"].as_i64().unwrap();
+    let secret = issued["key"].as_str().unwrap().to_string();
+    assert_eq!(issued["scopes"], json!(["read:balance"]));
+    assert!(secret.starts_with(issued["prefix"].as_str().unwrap()));
+
+    let balance = |secret: &str| {
+        test::TestRequest::get()
+            .uri("/api/wallet/balance/0x0000000000000000000000000000000000000000?asset=ETH")
+            .insert_header(bearer(secret))
+            .to_request()
+    };
+    let response = test::call_service(&app, balance(&secret)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The reports that scan every account are reserved to admins.
+    for uri in [
+        "/api/ledger/balances/check",
+        "/api/ledger/balances/negative",
+    ] {
+        let request = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(bearer(&secret))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    // Issuing keys is reserved to admins.
+    let request = test::TestRequest::post()
+        .uri("/api/keys")
+        .insert_header(bearer(&secret))
+        .set_json(json!({ "name": "escalate", "scopes": ["admin"] }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/keys/{}/rotate", id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let rotated: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    let rotated_secret = rotated["key"].as_str().unwrap().to_string();
+    assert_eq!(rotated["id"], id);
+    assert_ne!(rotated_secret, secret);
+
+    let response = test::call_service(&app, balance(&secret)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&app, balance(&rotated_secret)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/keys/{}/revoke", id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let revoked: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert!(revoked["revoked_at"].is_string());
+
+    let response = test::call_service(&app, balance(&rotated_secret)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/keys/{}/rotate", id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
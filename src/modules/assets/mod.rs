@@ -0,0 +1,81 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::assets::services::{get_asset, get_assets, register_asset};
+use actix_web::web;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub mod repository;
+mod request;
+mod services;
+
+/// Asset assumed when a request does not name one.
+pub const DEFAULT_ASSET: &str = "ETH";
+
+/// Amounts are stored as `NUMERIC(38,18)`, so no asset can be more precise.
+pub const MAX_ASSET_DECIMALS: i16 = 18;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_assets)
+        .service(get_asset)
+        .service(register_asset);
+}
+
+/// Entry of the asset registry. Native assets have no `contract_address`;
+/// tokens are identified by theirs.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct Asset {
+    pub symbol: String,
+    pub decimals: i16,
+    pub contract_address: Option<String>,
+}
+
+impl Asset {
+    /// Rejects amounts with more decimal places than the asset can represent.
+    pub fn validate_amount(&self, amount: Decimal) -> Option<FieldError> {
+        if amount.normalize().scale() > self.decimals as u32 {
+            Some(FieldError::new(
+                ErrorCode::InvalidPrecision,
+                "amount",
+                format!(
+                    "{} amounts cannot have more than {} decimal places.",
+                    self.symbol, self.decimals
+                ),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usdc() -> Asset {
+        Asset {
+            symbol: "USDC".to_string(),
+            decimals: 6,
+            contract_address: Some("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_amount_within_precision() {
+        assert_eq!(usdc().validate_amount(Decimal::new(1_234_567, 6)), None);
+        // Trailing zeros do not count towards the precision.
+        assert_eq!(usdc().validate_amount(Decimal::new(1_000_000, 9)), None);
+    }
+
+    #[test]
+    fn test_amount_too_precise() {
+        assert_eq!(
+            usdc().validate_amount(Decimal::new(1, 7)),
+            Some(FieldError::new(
+                ErrorCode::InvalidPrecision,
+                "amount",
+                "USDC amounts cannot have more than 6 decimal places."
+            ))
+        );
+    }
+}
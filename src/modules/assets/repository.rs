@@ -0,0 +1,44 @@
+use crate::modules::assets::Asset;
+use sqlx::{PgExecutor, PgPool};
+
+pub(crate) async fn get_all_assets(pool: &PgPool) -> Result<Vec<Asset>, sqlx::Error> {
+    let assets = sqlx::query_as::<_, Asset>(
+        "SELECT symbol, decimals, contract_address FROM assets ORDER BY symbol",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(assets)
+}
+
+/// Registry entry for `symbol`, or `None` when the asset is unknown.
+pub async fn find_asset<'e, E>(executor: E, symbol: &str) -> Result<Option<Asset>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let asset = sqlx::query_as::<_, Asset>(
+        "SELECT symbol, decimals, contract_address FROM assets WHERE symbol = $1",
+    )
+    .bind(symbol)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(asset)
+}
+
+/// Registers `asset`, returning `false` when its symbol or contract address is
+/// already taken.
+pub(crate) async fn insert_asset(pool: &PgPool, asset: &Asset) -> Result<bool, sqlx::Error> {
+    let inserted = sqlx::query(
+        "INSERT INTO assets (symbol, decimals, contract_address) VALUES ($1, $2, $3)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(&asset.symbol)
+    .bind(asset.decimals)
+    .bind(&asset.contract_address)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(inserted == 1)
+}
@@ -0,0 +1,51 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::assets::{Asset, MAX_ASSET_DECIMALS};
+use regex::Regex;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RegisterAssetRequest {
+    pub symbol: String,
+    pub decimals: i16,
+    pub contract_address: Option<String>,
+}
+
+impl RegisterAssetRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut result = vec![];
+
+        let symbol_regex = Regex::new(r"^[A-Z0-9]{1,16}$").unwrap();
+        if !symbol_regex.is_match(&self.symbol) {
+            result.push(FieldError::new(
+                ErrorCode::InvalidSymbol,
+                "symbol",
+                "Symbol must be 1 to 16 uppercase letters or digits.",
+            ));
+        }
+        if !(0..=MAX_ASSET_DECIMALS).contains(&self.decimals) {
+            result.push(FieldError::new(
+                ErrorCode::InvalidDecimals,
+                "decimals",
+                format!("Decimals must be between 0 and {}.", MAX_ASSET_DECIMALS),
+            ));
+        }
+        if let Some(Err(err)) = self.contract_address.as_deref().map(address::normalize) {
+            result.push(err.for_field("contract_address", "contract address"));
+        }
+
+        result
+    }
+}
+
+impl From<RegisterAssetRequest> for Asset {
+    fn from(request: RegisterAssetRequest) -> Self {
+        Asset {
+            symbol: request.symbol,
+            decimals: request.decimals,
+            contract_address: request
+                .contract_address
+                .map(|contract_address| address::canonical(&contract_address)),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::api::build_json_response;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::modules::assets::Asset;
+use crate::modules::assets::repository::{find_asset, get_all_assets, insert_asset};
+use crate::modules::assets::request::RegisterAssetRequest;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, get, post, web};
+use sqlx::PgPool;
+
+#[get("")]
+async fn get_assets(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let assets = get_all_assets(&pool).await?;
+    Ok(build_json_response(assets, StatusCode::OK))
+}
+
+#[get("{symbol}")]
+async fn get_asset(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let asset = find_asset(pool.get_ref(), &path.into_inner())
+        .await?
+        .ok_or_else(|| ApiError::not_found(ErrorCode::AssetNotFound, "Asset not found."))?;
+
+    Ok(build_json_response(asset, StatusCode::OK))
+}
+
+#[post("")]
+async fn register_asset(
+    pool: web::Data<PgPool>,
+    body: web::Json<RegisterAssetRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let request = body.into_inner();
+
+    let errors = request.validate();
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let asset = Asset::from(request);
+
+    if !insert_asset(&pool, &asset).await? {
+        return Err(ApiError::conflict(
+            ErrorCode::AssetExists,
+            "An asset with this symbol or contract address already exists.",
+        ));
+    }
+
+    Ok(build_json_response(asset, StatusCode::CREATED))
+}
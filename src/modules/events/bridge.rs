@@ -0,0 +1,62 @@
+use crate::modules::events::{Event, EventBus};
+use actix_web::rt::time::sleep;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// PostgreSQL channel events are exchanged on.
+pub const CHANNEL: &str = "transaction_events";
+
+/// How long to wait before listening again after the connection failed.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize)]
+struct Notification {
+    origin: u64,
+    event: Event,
+}
+
+/// Sends `event` to every instance listening on [`CHANNEL`].
+pub(crate) async fn notify(pool: &PgPool, origin: u64, event: &Event) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_string(&Notification {
+        origin,
+        event: event.clone(),
+    })
+    .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CHANNEL)
+        .bind(payload)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Delivers the events other instances publish to the subscribers of `bus`.
+/// Events from `bus` itself were already delivered locally and are skipped.
+pub async fn run(pool: PgPool, bus: Arc<EventBus>) {
+    loop {
+        if let Err(e) = listen(&pool, &bus).await {
+            error!("Event bridge lost its connection: {}", e);
+        }
+        sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn listen(pool: &PgPool, bus: &EventBus) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+
+    loop {
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<Notification>(notification.payload()) {
+            Ok(notification) if notification.origin == bus.origin => {}
+            Ok(notification) => bus.deliver(notification.event),
+            Err(e) => warn!("Ignoring malformed event notification: {}", e),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+pub mod bridge;
+mod services;
+
+#[cfg(test)]
+pub mod test;
+
+use crate::modules::events::services::{stream_events, stream_events_ws};
+use crate::modules::transactions::TransactionStatus;
+use crate::modules::transactions::repository::TransactionRepository;
+use crate::modules::transactions::response::Transaction;
+use actix_web::web;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use tracing::error;
+
+/// Events a slow subscriber may fall behind by before it starts missing them.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Streams registered ahead of the transaction routes, so that `stream` is
+/// not taken for an address.
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(stream_events).service(stream_events_ws);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    /// A transaction was created.
+    Transaction,
+    /// The stored balance of an address changed.
+    Balance,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Transaction => "transaction",
+            EventKind::Balance => "balance",
+        }
+    }
+}
+
+/// Something subscribers are told about. `data` is sent to them as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    /// Addresses the event concerns, for filtering.
+    pub addresses: Vec<String>,
+    pub data: serde_json::Value,
+}
+
+impl Event {
+    pub fn transaction(transaction: &Transaction) -> Self {
+        Event {
+            kind: EventKind::Transaction,
+            addresses: vec![
+                transaction.address_from.clone(),
+                transaction.address_to.clone(),
+            ],
+            data: json!(transaction),
+        }
+    }
+
+    pub fn balance(address: &str, asset: &str, balance: Decimal) -> Self {
+        Event {
+            kind: EventKind::Balance,
+            addresses: vec![address.to_string()],
+            data: json!({ "address": address, "asset": asset, "balance": balance }),
+        }
+    }
+
+    /// Whether a subscriber filtering on `address`, if any, wants the event.
+    pub fn concerns(&self, address: Option<&str>) -> bool {
+        address.is_none_or(|address| self.addresses.iter().any(|a| a == address))
+    }
+}
+
+/// Fans events out to the streams of this process. With a bridge, events are
+/// also sent to every other instance through PostgreSQL `NOTIFY`, and
+/// [`bridge::run`] delivers theirs here.
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    /// Tells this instance's notifications apart from the others'.
+    origin: u64,
+    pool: Option<PgPool>,
+}
+
+impl EventBus {
+    /// A bus that only reaches the streams of this process.
+    pub fn new() -> Self {
+        EventBus {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            origin: OsRng.next_u64(),
+            pool: None,
+        }
+    }
+
+    /// A bus that also reaches the other instances sharing `pool`'s database.
+    pub fn with_bridge(pool: PgPool) -> Self {
+        EventBus {
+            pool: Some(pool),
+            ..EventBus::new()
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Delivers `event` to local subscribers and, with a bridge, to other
+    /// instances. Failing to notify them is logged rather than returned, as
+    /// the change the event reports has already been committed.
+    pub async fn publish(&self, event: Event) {
+        if let Some(pool) = &self.pool
+            && let Err(e) = bridge::notify(pool, self.origin, &event).await
+        {
+            error!("Failed to forward event to other instances: {}", e);
+        }
+
+        // Sending only fails when nobody is subscribed.
+        let _ = self.sender.send(event);
+    }
+
+    /// Delivers an event received from another instance to local subscribers.
+    fn deliver(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+/// Publishes transaction `id`, and the balances it changed. Called after the
+/// transaction has been committed, so lookup failures are logged rather than
+/// returned.
+pub async fn publish_transaction(
+    events: &EventBus,
+    repository: &dyn TransactionRepository,
+    id: i32,
+) {
+    match repository.find(id).await {
+        Ok(Some(transaction)) => {
+            events.publish(Event::transaction(&transaction)).await;
+            publish_balances(events, repository, &transaction).await;
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to load transaction {} for its event: {}", id, e),
+    }
+}
+
+/// Publishes the balances of both addresses of `transaction` once it is
+/// confirmed; until then it has not changed them.
+pub async fn publish_balances(
+    events: &EventBus,
+    repository: &dyn TransactionRepository,
+    transaction: &Transaction,
+) {
+    if transaction.status != TransactionStatus::Confirmed {
+        return;
+    }
+
+    for address in [&transaction.address_from, &transaction.address_to] {
+        match repository
+            .balances(address, Some(&transaction.asset), None, None)
+            .await
+        {
+            Ok(balances) => {
+                for balance in balances {
+                    events
+                        .publish(Event::balance(address, &balance.asset, balance.balance))
+                        .await;
+                }
+            }
+            Err(e) => error!(
+                "Failed to load the balance of {} for its event: {}",
+                address, e
+            ),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::api::error::ApiError;
+use crate::modules::address;
+use crate::modules::events::{Event, EventBus};
+use actix_web::rt::time::timeout;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use actix_ws::Message;
+use futures_util::StreamExt;
+use futures_util::stream;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Idle time after which a comment is sent, so that proxies keep the
+/// connection open.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    /// Only events concerning this address are sent.
+    address: Option<String>,
+}
+
+/// Server-Sent Events: each event is sent under its type, with its data as
+/// JSON.
+#[get("stream")]
+async fn stream_events(
+    events: web::Data<EventBus>,
+    query: web::Query<StreamQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let address = query.into_inner().address.map(|a| address::canonical(&a));
+    let receiver = events.subscribe();
+
+    let body = stream::unfold(receiver, move |mut receiver| {
+        let address = address.clone();
+        async move {
+            let chunk =
+                match timeout(KEEP_ALIVE, next_event(&mut receiver, address.as_deref())).await {
+                    Ok(Some(event)) => {
+                        format!("event: {}\ndata: {}\n\n", event.kind.as_str(), event.data)
+                    }
+                    Ok(None) => return None,
+                    Err(_) => ": keep-alive\n\n".to_string(),
+                };
+            Some((Ok::<_, actix_web::Error>(Bytes::from(chunk)), receiver))
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body))
+}
+
+/// WebSocket: each event is sent as a JSON text message with its `type`,
+/// `addresses` and `data`. Messages from the client other than pings and
+/// close are ignored.
+#[get("stream/ws")]
+async fn stream_events_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    events: web::Data<EventBus>,
+    query: web::Query<StreamQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let address = query.into_inner().address.map(|a| address::canonical(&a));
+    let mut receiver = events.subscribe();
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                event = next_event(&mut receiver, address.as_deref()) => {
+                    let Some(event) = event else { break };
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if session.text(text).await.is_err() {
+                        return;
+                    }
+                }
+                message = messages.next() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+/// The next event concerning `address`, or `None` once the bus is gone.
+/// Events missed by falling behind are skipped.
+async fn next_event(receiver: &mut Receiver<Event>, address: Option<&str>) -> Option<Event> {
+    loop {
+        match receiver.recv().await {
+            Ok(event) if event.concerns(address) => return Some(event),
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
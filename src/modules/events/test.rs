@@ -0,0 +1,161 @@
+use crate::modules::events::{Event, EventBus, bridge};
+use crate::modules::transactions::memory::InMemoryTransactionRepository;
+use crate::modules::transactions::repository::TransactionRepository;
+use crate::modules::transactions::test::{test_config, test_pool, unique_address, unlimited};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::http::StatusCode;
+use actix_web::rt::time::{sleep, timeout};
+use actix_web::{App, test, web};
+use futures_util::future::poll_fn;
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Reads the next Server-Sent Event, skipping keep-alive comments.
+async fn next_sse(body: &mut BoxBody) -> String {
+    loop {
+        let chunk = timeout(
+            Duration::from_secs(5),
+            poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)),
+        )
+        .await
+        .expect("no event within 5 seconds")
+        .expect("stream ended")
+        .unwrap();
+        let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+        if !chunk.starts_with(':') {
+            return chunk;
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_stream_pushes_transactions_and_balances_of_an_address() {
+    let memory = InMemoryTransactionRepository::new();
+    memory.set_account_policy("0x1111111111111111111111111111111111111111", unlimited());
+    let repository: Arc<dyn TransactionRepository> = Arc::new(memory);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::from(repository))
+            .app_data(web::Data::new(EventBus::new()))
+            .service(
+                web::scope("/api/transactions")
+                    .configure(crate::modules::events::api_config)
+                    .configure(crate::modules::transactions::api_config),
+            ),
+    )
+    .await;
+
+    let funder = "0x1111111111111111111111111111111111111111";
+    let address = "0x2222222222222222222222222222222222222222";
+    let other = "0x3333333333333333333333333333333333333333";
+
+    let request = test::TestRequest::get()
+        .uri(&format!(
+            "/api/transactions/stream?address={}",
+            address.to_uppercase().replace("0X", "0x")
+        ))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut stream = response.into_body();
+
+    for (to, amount) in [(other, "5"), (address, "10")] {
+        let request = test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": funder,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": "Deposit",
+            }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // The deposit to the other address is filtered out.
+    let event = next_sse(&mut stream).await;
+    let (kind, data) = event.trim_end().split_once('\n').unwrap();
+    assert_eq!(kind, "event: transaction");
+    let transaction: serde_json::Value =
+        serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(transaction["address_to"], address);
+    assert_eq!(transaction["amount"], "10");
+
+    // Only the balance of the watched address, not the funder's.
+    let event = next_sse(&mut stream).await;
+    let (kind, data) = event.trim_end().split_once('\n').unwrap();
+    assert_eq!(kind, "event: balance");
+    let balance: serde_json::Value =
+        serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(
+        balance,
+        json!({ "address": address, "asset": "ETH", "balance": "10" })
+    );
+
+    // The WebSocket endpoint only answers upgrade requests.
+    let request = test::TestRequest::get()
+        .uri("/api/transactions/stream/ws")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_bridge_delivers_events_between_instances_once() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(38);
+
+    let here = Arc::new(EventBus::with_bridge(pool.clone()));
+    let there = Arc::new(EventBus::with_bridge(pool.clone()));
+    let bridges = [
+        actix_web::rt::spawn(bridge::run(pool.clone(), here.clone())),
+        actix_web::rt::spawn(bridge::run(pool.clone(), there.clone())),
+    ];
+    // Give both bridges time to start listening.
+    sleep(Duration::from_millis(500)).await;
+
+    let mut received_here = here.subscribe();
+    let mut received_there = there.subscribe();
+
+    here.publish(Event::balance(&address, "ETH", Decimal::ONE))
+        .await;
+
+    for receiver in [&mut received_here, &mut received_there] {
+        let event = timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("no event within 5 seconds")
+            .unwrap();
+        assert_eq!(event.addresses, vec![address.clone()]);
+    }
+
+    // The bridge does not hand an instance back its own events.
+    let echo = timeout(Duration::from_millis(500), async {
+        loop {
+            let event = received_here.recv().await.unwrap();
+            if event.concerns(Some(&address)) {
+                return event;
+            }
+        }
+    })
+    .await;
+    assert!(echo.is_err());
+
+    // Listeners must be dropped while the runtime is still running.
+    for bridge in bridges {
+        bridge.abort();
+        let _ = bridge.await;
+    }
+}
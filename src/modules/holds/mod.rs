@@ -0,0 +1,31 @@
+use crate::modules::holds::services::{capture_hold, create_hold, get_hold, release_hold};
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+
+pub mod repository;
+mod request;
+pub mod response;
+mod services;
+pub mod sweeper;
+
+#[cfg(test)]
+pub mod test;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_hold)
+        .service(get_hold)
+        .service(capture_hold)
+        .service(release_hold);
+}
+
+/// Lifecycle of a hold. While active, its amount is subtracted from the
+/// available balance of the address; every other state is final.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum HoldStatus {
+    Active,
+    Captured,
+    Released,
+    Expired,
+}
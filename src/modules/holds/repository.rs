@@ -0,0 +1,110 @@
+use crate::modules::holds::HoldStatus;
+use crate::modules::holds::response::Hold;
+use rust_decimal::Decimal;
+use sqlx::{PgExecutor, PgPool};
+use time::PrimitiveDateTime;
+
+pub(crate) async fn insert_hold<'e, E>(
+    executor: E,
+    address: &str,
+    asset: &str,
+    amount: Decimal,
+    expires_at: PrimitiveDateTime,
+) -> Result<Hold, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let hold = sqlx::query_as::<_, Hold>(
+        "INSERT INTO holds (address, asset, amount, expires_at)
+         VALUES ($1, $2, $3, $4)
+         RETURNING *",
+    )
+    .bind(address)
+    .bind(asset)
+    .bind(amount)
+    .bind(expires_at)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(hold)
+}
+
+pub(crate) async fn get_hold_by_id(pool: &PgPool, id: i32) -> Result<Option<Hold>, sqlx::Error> {
+    let hold = sqlx::query_as::<_, Hold>("SELECT * FROM holds WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(hold)
+}
+
+/// Loads hold `id`, locking its row until the surrounding database
+/// transaction ends.
+pub(crate) async fn get_hold_for_update<'e, E>(
+    executor: E,
+    id: i32,
+) -> Result<Option<Hold>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let hold = sqlx::query_as::<_, Hold>("SELECT * FROM holds WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(hold)
+}
+
+/// Marks hold `id` as captured by `transaction_id` for `amount`.
+pub(crate) async fn set_hold_captured<'e, E>(
+    executor: E,
+    id: i32,
+    amount: Decimal,
+    transaction_id: i32,
+) -> Result<Hold, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let hold = sqlx::query_as::<_, Hold>(
+        "UPDATE holds SET status = 'captured', captured_amount = $2, transaction_id = $3
+         WHERE id = $1
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(amount)
+    .bind(transaction_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(hold)
+}
+
+pub(crate) async fn set_hold_status<'e, E>(
+    executor: E,
+    id: i32,
+    status: HoldStatus,
+) -> Result<Hold, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let hold = sqlx::query_as::<_, Hold>("UPDATE holds SET status = $2 WHERE id = $1 RETURNING *")
+        .bind(id)
+        .bind(status)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(hold)
+}
+
+/// Marks every active hold past its expiry as expired and returns how many
+/// were released.
+pub(crate) async fn expire_holds(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE holds SET status = 'expired'
+         WHERE status = 'active' AND expires_at <= NOW()",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
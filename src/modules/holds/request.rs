@@ -0,0 +1,86 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::transactions::request::default_asset;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+/// Body of the hold creation endpoint. `expires_at` is RFC 3339.
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateHoldRequest {
+    pub address: String,
+    pub amount: Decimal,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+impl CreateHoldRequest {
+    pub fn validate(&self, now: OffsetDateTime) -> Vec<FieldError> {
+        let mut result = vec![];
+
+        if let Err(err) = address::normalize(&self.address) {
+            result.push(err.for_field("address", "address"));
+        }
+        if self.amount <= Decimal::ZERO {
+            result.push(FieldError::new(
+                ErrorCode::InvalidAmount,
+                "amount",
+                "Hold amount must be greater than zero.",
+            ));
+        }
+        if self.expires_at <= now {
+            result.push(FieldError::new(
+                ErrorCode::InvalidExpiry,
+                "expires_at",
+                "expires_at must be in the future.",
+            ));
+        }
+
+        result
+    }
+}
+
+/// Body of the capture endpoint. Without `amount` the whole hold is captured;
+/// a smaller amount captures part of it and releases the rest.
+#[derive(Debug, Deserialize)]
+pub(crate) struct CaptureHoldRequest {
+    pub address_to: String,
+    pub amount: Option<Decimal>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Duration;
+
+    fn request(amount: Decimal, expires_in: Duration) -> CreateHoldRequest {
+        CreateHoldRequest {
+            address: "0x1234567890abcdef1234567890abcdef12345678".to_string(),
+            amount,
+            asset: "ETH".to_string(),
+            expires_at: OffsetDateTime::now_utc() + expires_in,
+        }
+    }
+
+    #[test]
+    fn test_valid_hold() {
+        let hold = request(Decimal::new(10, 0), Duration::MINUTE);
+        assert!(hold.validate(OffsetDateTime::now_utc()).is_empty());
+    }
+
+    #[test]
+    fn test_hold_must_expire_in_the_future() {
+        let hold = request(Decimal::ZERO, -Duration::MINUTE);
+        let codes: Vec<ErrorCode> = hold
+            .validate(OffsetDateTime::now_utc())
+            .into_iter()
+            .map(|error| error.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![ErrorCode::InvalidAmount, ErrorCode::InvalidExpiry]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use crate::modules::holds::HoldStatus;
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+use sqlx::FromRow;
+use time::PrimitiveDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Funds reserved on `address` until `expires_at`. Once captured,
+/// `captured_amount` was sent by transaction `transaction_id`.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Hold {
+    pub id: i32,
+    pub address: String,
+    pub asset: String,
+    pub amount: Decimal,
+    pub captured_amount: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<i32>,
+    pub status: HoldStatus,
+    #[serde(serialize_with = "serialize_utc")]
+    pub expires_at: PrimitiveDateTime,
+    #[serde(serialize_with = "serialize_utc")]
+    pub created_at: PrimitiveDateTime,
+}
+
+impl Hold {
+    /// Whether the hold still reserves funds at `now`, even if the sweeper has
+    /// not marked it expired yet.
+    pub fn is_active(&self, now: PrimitiveDateTime) -> bool {
+        self.status == HoldStatus::Active && self.expires_at > now
+    }
+}
+
+pub(crate) fn serialize_utc<S>(date: &PrimitiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let date_string = date
+        .assume_utc()
+        .format(&Rfc3339)
+        .map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&date_string)
+}
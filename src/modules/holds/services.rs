@@ -0,0 +1,235 @@
+use crate::api::build_json_response;
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::configurations::Config;
+use crate::modules::address;
+use crate::modules::api_keys::response::ApiKey;
+use crate::modules::assets::repository::find_asset;
+use crate::modules::events::{EventBus, publish_transaction};
+use crate::modules::holds::HoldStatus;
+use crate::modules::holds::repository::{
+    get_hold_by_id, get_hold_for_update, insert_hold, set_hold_captured, set_hold_status,
+};
+use crate::modules::holds::request::{CaptureHoldRequest, CreateHoldRequest};
+use crate::modules::holds::response::Hold;
+use crate::modules::metrics::metrics;
+use crate::modules::transactions::repository::{TransactionRepository, lock_address};
+use crate::modules::transactions::request::to_utc_primitive;
+use crate::modules::transactions::response::Transaction;
+use crate::modules::transactions::{TransactionStatus, TransactionType};
+use crate::modules::users::ensure_may_spend;
+use crate::modules::users::repository::get_address_owner;
+use crate::modules::wallet::repository::{get_account_policy, get_available_balance};
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, get, post, web};
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+
+#[post("")]
+async fn create_hold(
+    pool: web::Data<PgPool>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    body: web::Json<CreateHoldRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request = body.into_inner();
+
+    let mut errors = request.validate(OffsetDateTime::now_utc());
+    match find_asset(pool.get_ref(), &request.asset).await? {
+        Some(asset) => errors.extend(asset.validate_amount(request.amount)),
+        None => errors.push(FieldError::new(
+            ErrorCode::UnknownAsset,
+            "asset",
+            "Unknown asset.",
+        )),
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+    request.address = address::canonical(&request.address);
+
+    let mut db_tx = pool.begin().await?;
+
+    // Holds draw on the same funds as withdrawals, so they serialize on the
+    // same address lock.
+    lock_address(&mut *db_tx, &request.address).await?;
+
+    let owner = get_address_owner(&mut *db_tx, &request.address).await?;
+    ensure_may_spend(api_key.as_deref(), owner, Some("address"))?;
+
+    let balance = get_available_balance(&mut *db_tx, &request.address, &request.asset).await?;
+    let policy = get_account_policy(&mut *db_tx, &request.address)
+        .await?
+        .unwrap_or_default();
+
+    if !policy.allows(balance, request.amount) {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            ErrorCode::InsufficientBalance,
+            "amount",
+            "Insufficient balance",
+        )]));
+    }
+
+    let hold = insert_hold(
+        &mut *db_tx,
+        &request.address,
+        &request.asset,
+        request.amount,
+        to_utc_primitive(request.expires_at),
+    )
+    .await?;
+
+    db_tx.commit().await?;
+
+    Ok(build_json_response(hold, StatusCode::CREATED))
+}
+
+#[get("{id}")]
+async fn get_hold(
+    pool: web::Data<PgPool>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let hold = get_hold_by_id(&pool, path.into_inner())
+        .await?
+        .ok_or_else(hold_not_found)?;
+
+    let owner = get_address_owner(pool.get_ref(), &hold.address).await?;
+    ensure_may_spend(api_key.as_deref(), owner, None)?;
+
+    Ok(build_json_response(hold, StatusCode::OK))
+}
+
+#[post("{id}/capture")]
+async fn capture_hold(
+    config: web::Data<Config>,
+    pool: web::Data<PgPool>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+    body: web::Json<CaptureHoldRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let mut request = body.into_inner();
+    if let Ok(address_to) = address::normalize(&request.address_to) {
+        request.address_to = address_to;
+    }
+
+    let mut db_tx = pool.begin().await?;
+
+    let hold = lock_active_hold(&mut db_tx, id).await?;
+
+    let owner = get_address_owner(&mut *db_tx, &hold.address).await?;
+    ensure_may_spend(api_key.as_deref(), owner, None)?;
+
+    // A capture is not signed by the held address, so when signatures are
+    // required it may only move funds between addresses of the same owner.
+    if config.signatures.required && request.address_to != hold.address {
+        let recipient = get_address_owner(&mut *db_tx, &request.address_to).await?;
+        if owner.is_none() || recipient != owner {
+            return Err(ApiError::Validation(vec![FieldError::new(
+                ErrorCode::SignatureRequired,
+                "address_to",
+                "Holds can only be captured to addresses of their owner when signatures are required.",
+            )]));
+        }
+    }
+
+    let amount = request.amount.unwrap_or(hold.amount);
+    if amount > hold.amount {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            ErrorCode::InvalidAmount,
+            "amount",
+            "Cannot capture more than the held amount.",
+        )]));
+    }
+
+    let transaction = Transaction {
+        id: None,
+        address_from: hold.address.clone(),
+        address_to: request.address_to,
+        amount,
+        asset: hold.asset.clone(),
+        transaction_type: TransactionType::Withdrawal,
+        created_at: None,
+        reversal_of: None,
+        reversed_by: None,
+        status: TransactionStatus::Confirmed,
+        api_key_id: api_key.map(|api_key| api_key.id),
+        nonce: None,
+        settled_by_api_key_id: None,
+    };
+
+    let balance = get_available_balance(&mut *db_tx, &hold.address, &hold.asset).await?;
+    let policy = get_account_policy(&mut *db_tx, &hold.address)
+        .await?
+        .unwrap_or_default();
+    let asset = find_asset(&mut *db_tx, &hold.asset).await?;
+
+    // The funds being captured are the ones this hold has been reserving.
+    let errors = transaction.validate(balance + hold.amount, &policy, asset.as_ref());
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let transaction_id = transaction.insert(&mut db_tx).await?;
+    let hold = set_hold_captured(&mut *db_tx, id, amount, transaction_id).await?;
+    db_tx.commit().await?;
+    metrics().transaction_created(TransactionType::Withdrawal);
+
+    publish_transaction(&events, repository.get_ref(), transaction_id).await;
+
+    Ok(build_json_response(hold, StatusCode::OK))
+}
+
+#[post("{id}/release")]
+async fn release_hold(
+    pool: web::Data<PgPool>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    let mut db_tx = pool.begin().await?;
+
+    let hold = lock_active_hold(&mut db_tx, id).await?;
+
+    let owner = get_address_owner(&mut *db_tx, &hold.address).await?;
+    ensure_may_spend(api_key.as_deref(), owner, None)?;
+
+    let hold = set_hold_status(&mut *db_tx, id, HoldStatus::Released).await?;
+    db_tx.commit().await?;
+
+    Ok(build_json_response(hold, StatusCode::OK))
+}
+
+/// Locks hold `id` and the funds of its address, or explains why the hold can
+/// no longer be settled.
+async fn lock_active_hold(conn: &mut PgConnection, id: i32) -> Result<Hold, ApiError> {
+    let hold = get_hold_for_update(&mut *conn, id)
+        .await?
+        .ok_or_else(hold_not_found)?;
+
+    if !hold.is_active(to_utc_primitive(OffsetDateTime::now_utc())) {
+        return Err(match hold.status {
+            HoldStatus::Active | HoldStatus::Expired => {
+                ApiError::conflict(ErrorCode::HoldExpired, "Hold has expired.")
+            }
+            HoldStatus::Captured => ApiError::conflict(
+                ErrorCode::HoldAlreadySettled,
+                "Hold has already been captured.",
+            ),
+            HoldStatus::Released => ApiError::conflict(
+                ErrorCode::HoldAlreadySettled,
+                "Hold has already been released.",
+            ),
+        });
+    }
+
+    lock_address(&mut *conn, &hold.address).await?;
+
+    Ok(hold)
+}
+
+fn hold_not_found() -> ApiError {
+    ApiError::not_found(ErrorCode::HoldNotFound, "Hold not found.")
+}
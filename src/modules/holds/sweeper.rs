@@ -0,0 +1,21 @@
+use crate::modules::holds::repository::expire_holds;
+use actix_web::rt::time::interval;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Releases expired holds every `period`. Available balances already ignore
+/// holds past their expiry, so the sweeper only keeps their status accurate.
+pub async fn run(pool: PgPool, period: Duration) {
+    let mut ticker = interval(period);
+
+    loop {
+        ticker.tick().await;
+
+        match expire_holds(&pool).await {
+            Ok(0) => {}
+            Ok(released) => info!("Released {} expired holds", released),
+            Err(e) => error!("Failed to release expired holds: {}", e),
+        }
+    }
+}
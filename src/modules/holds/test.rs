@@ -0,0 +1,171 @@
+use crate::modules::assets::DEFAULT_ASSET;
+use crate::modules::events::EventBus;
+use crate::modules::holds::api_config;
+use crate::modules::holds::repository::expire_holds;
+use crate::modules::transactions::test::{
+    issuer, pg_repository, test_config, test_pool, unique_address,
+};
+use crate::modules::wallet::repository::{get_available_balance, get_stored_balance};
+use actix_web::http::StatusCode;
+use actix_web::{App, test, web};
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+#[actix_web::test]
+async fn test_holds_reserve_funds_until_captured_released_or_expired() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 30).await;
+    let address = unique_address(31);
+    let recipient = unique_address(32);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(
+                web::scope("/api/transactions").configure(crate::modules::transactions::api_config),
+            )
+            .service(web::scope("/api/holds").configure(api_config)),
+    )
+    .await;
+
+    let deposit = test::TestRequest::post()
+        .uri("/api/transactions")
+        .set_json(json!({
+            "address_from": funder,
+            "address_to": address,
+            "amount": "100",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    let _: i32 = test::call_and_read_body_json(&app, deposit).await;
+
+    let hold = |amount: &str, expires_in: time::Duration| {
+        let expires_at = (OffsetDateTime::now_utc() + expires_in)
+            .format(&Rfc3339)
+            .unwrap();
+        test::TestRequest::post()
+            .uri("/api/holds")
+            .set_json(json!({
+                "address": address,
+                "amount": amount,
+                "expires_at": expires_at,
+            }))
+            .to_request()
+    };
+    let capture = |id: &serde_json::Value, amount: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/holds/{}/capture", id))
+            .set_json(json!({ "address_to": recipient, "amount": amount }))
+            .to_request()
+    };
+    let release = |id: &serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("/api/holds/{}/release", id))
+            .to_request()
+    };
+
+    let response = test::call_service(&app, hold("60", time::Duration::HOUR)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let captured: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::new(40, 0)
+    );
+
+    // Only 40 is left once 60 is reserved.
+    assert_eq!(
+        test::call_service(&app, hold("50", time::Duration::HOUR))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        test::call_service(&app, capture(&captured["id"], "61"))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // A partial capture sends 45 and gives the remaining 15 back.
+    let response = test::call_service(&app, capture(&captured["id"], "45")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "captured");
+    assert!(body["transaction_id"].is_i64());
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(55, 0))
+    );
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::new(55, 0)
+    );
+    assert_eq!(
+        test::call_service(&app, capture(&captured["id"], "10"))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+
+    let released: serde_json::Value =
+        test::call_and_read_body_json(&app, hold("20", time::Duration::HOUR)).await;
+    assert_eq!(
+        test::call_service(&app, release(&released["id"]))
+            .await
+            .status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        test::call_service(&app, release(&released["id"]))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+
+    let expiring: serde_json::Value =
+        test::call_and_read_body_json(&app, hold("55", time::Duration::SECOND)).await;
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::ZERO
+    );
+    actix_web::rt::time::sleep(Duration::from_millis(1100)).await;
+
+    // Expired holds stop reserving funds before the sweeper gets to them.
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::new(55, 0)
+    );
+    assert_eq!(
+        test::call_service(&app, capture(&expiring["id"], "55"))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+
+    assert!(expire_holds(&pool).await.unwrap() >= 1);
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/holds/{}", expiring["id"]))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["status"], "expired");
+}
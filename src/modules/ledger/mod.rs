@@ -0,0 +1,110 @@
+pub mod repository;
+mod services;
+
+use crate::modules::ledger::services::check_ledger;
+use actix_web::web;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(check_ledger);
+}
+
+/// One side of a journal entry. Credits are positive and debits negative, so
+/// the postings of a balanced transaction sum to zero.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct Posting {
+    pub account: String,
+    pub asset: String,
+    pub amount: Decimal,
+}
+
+impl Posting {
+    pub fn debit(account: &str, asset: &str, amount: Decimal) -> Self {
+        Posting {
+            account: account.to_string(),
+            asset: asset.to_string(),
+            amount: -amount,
+        }
+    }
+
+    pub fn credit(account: &str, asset: &str, amount: Decimal) -> Self {
+        Posting {
+            account: account.to_string(),
+            asset: asset.to_string(),
+            amount,
+        }
+    }
+}
+
+/// Result of checking that every transaction's postings sum to zero.
+#[derive(Debug, Serialize)]
+pub struct LedgerCheck {
+    pub balanced: bool,
+    pub unbalanced_transactions: Vec<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::transactions::response::Transaction;
+    use crate::modules::transactions::{TransactionStatus, TransactionType};
+    use rust_decimal::Decimal;
+
+    fn transaction(amount: Decimal, transaction_type: TransactionType) -> Transaction {
+        Transaction {
+            id: None,
+            address_from: "0xAAA1111111111111111111111111111111111111".to_string(),
+            address_to: "0xBBB2222222222222222222222222222222222222".to_string(),
+            amount,
+            asset: "ETH".to_string(),
+            transaction_type,
+            created_at: None,
+            reversal_of: None,
+            reversed_by: None,
+            status: TransactionStatus::Confirmed,
+            api_key_id: None,
+            nonce: None,
+            settled_by_api_key_id: None,
+        }
+    }
+
+    #[test]
+    fn test_postings_always_sum_to_zero() {
+        let amounts = [
+            Decimal::ZERO,
+            Decimal::new(1, 10),
+            Decimal::new(150, 0),
+            Decimal::new(123_456_789, 4),
+            Decimal::new(-20, 0),
+        ];
+        for amount in amounts {
+            for transaction_type in [TransactionType::Deposit, TransactionType::Withdrawal] {
+                let postings = transaction(amount, transaction_type).postings();
+                assert_eq!(postings.len(), 2);
+                assert_eq!(
+                    postings.iter().map(|p| p.amount).sum::<Decimal>(),
+                    Decimal::ZERO
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_postings_move_funds_from_source_to_destination() {
+        for transaction_type in [TransactionType::Deposit, TransactionType::Withdrawal] {
+            let tx = transaction(Decimal::new(10, 0), transaction_type);
+            let postings = tx.postings();
+            assert!(
+                postings
+                    .iter()
+                    .any(|p| p.account == tx.address_from && p.amount == Decimal::new(-10, 0))
+            );
+            assert!(
+                postings
+                    .iter()
+                    .any(|p| p.account == tx.address_to && p.amount == Decimal::new(10, 0))
+            );
+        }
+    }
+}
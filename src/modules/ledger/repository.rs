@@ -0,0 +1,49 @@
+use crate::modules::ledger::Posting;
+use crate::modules::wallet::repository::add_to_balance;
+use sqlx::{PgConnection, PgPool};
+
+/// Writes the postings of `transaction_id`, opening any account seen for the
+/// first time and updating the materialized balances.
+pub(crate) async fn insert_postings(
+    conn: &mut PgConnection,
+    transaction_id: i32,
+    postings: &[Posting],
+) -> Result<(), sqlx::Error> {
+    for posting in postings {
+        sqlx::query("INSERT INTO accounts (address) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(&posting.account)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO postings (transaction_id, account, asset, amount) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(transaction_id)
+        .bind(&posting.account)
+        .bind(&posting.asset)
+        .bind(posting.amount)
+        .execute(&mut *conn)
+        .await?;
+
+        add_to_balance(&mut *conn, &posting.account, &posting.asset, posting.amount).await?;
+    }
+
+    Ok(())
+}
+
+/// Ids of confirmed transactions whose postings are missing or do not sum to
+/// zero, and of unconfirmed transactions that have been posted anyway.
+pub(crate) async fn find_unbalanced_transactions(pool: &PgPool) -> Result<Vec<i32>, sqlx::Error> {
+    let ids = sqlx::query_scalar(
+        "SELECT t.id FROM transactions t
+         LEFT JOIN postings p ON p.transaction_id = t.id
+         GROUP BY t.id, t.status
+         HAVING (t.status = 'confirmed' AND (COUNT(p.id) = 0 OR SUM(p.amount) <> 0))
+             OR (t.status <> 'confirmed' AND COUNT(p.id) > 0)
+         ORDER BY t.id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids)
+}
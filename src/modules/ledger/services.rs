@@ -0,0 +1,20 @@
+use crate::api::build_json_response;
+use crate::api::error::ApiError;
+use crate::modules::ledger::LedgerCheck;
+use crate::modules::ledger::repository::find_unbalanced_transactions;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, get, web};
+use sqlx::PgPool;
+
+#[get("/check")]
+pub(super) async fn check_ledger(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let unbalanced_transactions = find_unbalanced_transactions(&pool).await?;
+
+    Ok(build_json_response(
+        LedgerCheck {
+            balanced: unbalanced_transactions.is_empty(),
+            unbalanced_transactions,
+        },
+        StatusCode::OK,
+    ))
+}
@@ -0,0 +1,160 @@
+use crate::api::error::ErrorCode;
+use crate::modules::metrics::services::get_metrics;
+use crate::modules::transactions::TransactionType;
+use actix_web::web;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use sqlx::PgPool;
+use std::sync::LazyLock;
+
+pub mod middleware;
+mod services;
+
+#[cfg(test)]
+mod test;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_metrics);
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics, shared by every worker.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Everything exposed on `/metrics`. Counters only cover this instance;
+/// Prometheus sums them across instances.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_max_connections: IntGauge,
+    transactions_created: IntCounterVec,
+    validation_failures: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled."),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests.",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Open database connections, by whether they are idle or in use.",
+            ),
+            &["state"],
+        )
+        .unwrap();
+        let db_pool_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Most connections the database pool opens.",
+        )
+        .unwrap();
+        let transactions_created = IntCounterVec::new(
+            Opts::new("transactions_created_total", "Transactions created."),
+            &["type"],
+        )
+        .unwrap();
+        let validation_failures = IntCounterVec::new(
+            Opts::new(
+                "validation_failures_total",
+                "Problems found in rejected requests and batch items.",
+            ),
+            &["code"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry
+            .register(Box::new(http_request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_pool_connections.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_pool_max_connections.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(transactions_created.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(validation_failures.clone()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            transactions_created,
+            validation_failures,
+        }
+    }
+
+    /// Records a handled request. `route` is the pattern it matched, such as
+    /// `/api/transactions/{address}`, so that addresses and ids do not each
+    /// get their own series.
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(seconds);
+    }
+
+    /// Records a committed transaction.
+    pub fn transaction_created(&self, transaction_type: TransactionType) {
+        self.transactions_created
+            .with_label_values(&[format!("{:?}", transaction_type)])
+            .inc();
+    }
+
+    /// Records a problem reported to a client.
+    pub fn validation_failed(&self, code: ErrorCode) {
+        let code = serde_json::to_value(code)
+            .ok()
+            .and_then(|code| code.as_str().map(str::to_string))
+            .unwrap_or_default();
+        self.validation_failures.with_label_values(&[code]).inc();
+    }
+
+    /// Every metric in the Prometheus text format, with the usage of `pool`
+    /// as of now.
+    pub fn render(&self, pool: &PgPool) -> String {
+        let open = pool.size() as i64;
+        let idle = pool.num_idle() as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(open - idle);
+        self.db_pool_max_connections
+            .set(pool.options().get_max_connections() as i64);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("metrics are valid");
+        String::from_utf8(buffer).expect("metrics are UTF-8")
+    }
+}
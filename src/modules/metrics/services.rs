@@ -0,0 +1,11 @@
+use crate::modules::metrics::metrics;
+use actix_web::{HttpResponse, get, web};
+use prometheus::TEXT_FORMAT;
+use sqlx::PgPool;
+
+#[get("")]
+async fn get_metrics(pool: web::Data<PgPool>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(TEXT_FORMAT)
+        .body(metrics().render(&pool))
+}
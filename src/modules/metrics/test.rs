@@ -0,0 +1,78 @@
+use crate::api::api_routes;
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::services::create_api_key;
+use crate::modules::events::EventBus;
+use crate::modules::metrics::middleware::RecordMetrics;
+use crate::modules::transactions::test::{
+    issuer, pg_repository, test_config, test_pool, unique_address,
+};
+use actix_web::http::{StatusCode, header};
+use actix_web::{App, test, web};
+use serde_json::json;
+
+#[actix_web::test]
+async fn test_metrics_cover_requests_pool_transactions_and_failures() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 50).await;
+    let address = unique_address(51);
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .wrap(RecordMetrics)
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::get().uri("/api/alive").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let request = test::TestRequest::get().uri("/no/such/route").to_request();
+    test::call_service(&app, request).await;
+
+    for to in [&address, &funder] {
+        let request = test::TestRequest::post()
+            .uri("/api/transactions")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", admin.key)))
+            .set_json(json!({
+                "address_from": funder,
+                "address_to": to,
+                "amount": "1",
+                "transaction_type": "Deposit",
+            }))
+            .to_request();
+        test::call_service(&app, request).await;
+    }
+
+    let request = test::TestRequest::get().uri("/metrics").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+
+    for expected in [
+        r#"http_requests_total{method="GET",route="/api/alive",status="200"}"#,
+        r#"http_requests_total{method="GET",route="unmatched",status="404"}"#,
+        r#"http_requests_total{method="POST",route="/api/transactions",status="201"}"#,
+        r#"http_request_duration_seconds_bucket{method="GET",route="/api/alive",status="200",le="0.005"}"#,
+        r#"db_pool_connections{state="in_use"}"#,
+        r#"db_pool_max_connections 10"#,
+        r#"transactions_created_total{type="Deposit"}"#,
+        r#"validation_failures_total{code="SAME_ADDRESS"}"#,
+    ] {
+        assert!(body.contains(expected), "missing {} in\n{}", expected, body);
+    }
+}
@@ -1,2 +1,12 @@
+pub mod address;
+pub mod api_keys;
+pub mod assets;
+pub mod events;
+pub mod holds;
+pub mod ledger;
+pub mod metrics;
+pub mod signature;
 pub mod transactions;
+pub mod users;
 pub mod wallet;
+pub mod webhooks;
@@ -0,0 +1,242 @@
+use crate::modules::transactions::TransactionType;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+
+/// EIP-712 domain every typed-data signature is bound to.
+const DOMAIN_NAME: &str = "r-r-challenge";
+const DOMAIN_VERSION: &str = "1";
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version)";
+const TRANSACTION_TYPE: &str = "Transaction(address from,address to,string amount,string asset,string transactionType,uint256 nonce)";
+
+/// How a transaction request was signed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// `personal_sign` over [`SignedTransaction::message`].
+    #[default]
+    Eip191,
+    /// `eth_signTypedData_v4` over a `Transaction` struct.
+    Eip712,
+}
+
+/// Why a signature could not be checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureError {
+    /// Not `0x` followed by 65 hex-encoded bytes.
+    Format,
+    /// No public key can be recovered from it, or it is malleable.
+    Invalid,
+}
+
+/// The fields of a transaction request covered by its signature. Addresses
+/// must be in canonical form.
+#[derive(Debug)]
+pub struct SignedTransaction<'a> {
+    pub address_from: &'a str,
+    pub address_to: &'a str,
+    pub amount: Decimal,
+    pub asset: &'a str,
+    pub transaction_type: TransactionType,
+    pub nonce: i64,
+}
+
+impl SignedTransaction<'_> {
+    /// Text signed under EIP-191. The amount is normalized, so `10.50` is
+    /// signed as `10.5`.
+    pub fn message(&self) -> String {
+        format!(
+            "Transfer {} {} from {} to {}\nType: {:?}\nNonce: {}",
+            self.amount.normalize(),
+            self.asset,
+            self.address_from,
+            self.address_to,
+            self.transaction_type,
+            self.nonce
+        )
+    }
+
+    /// The 32-byte hash the signer signed under `scheme`.
+    pub fn digest(&self, scheme: SignatureScheme) -> [u8; 32] {
+        match scheme {
+            SignatureScheme::Eip191 => {
+                let message = self.message();
+                let mut hasher = Keccak256::new();
+                hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()));
+                hasher.update(message);
+                hasher.finalize().into()
+            }
+            SignatureScheme::Eip712 => {
+                let domain = keccak([
+                    keccak([DOMAIN_TYPE.as_bytes()]),
+                    keccak([DOMAIN_NAME.as_bytes()]),
+                    keccak([DOMAIN_VERSION.as_bytes()]),
+                ]);
+                let amount = self.amount.normalize().to_string();
+                let transaction_type = format!("{:?}", self.transaction_type);
+                let message = keccak([
+                    keccak([TRANSACTION_TYPE.as_bytes()]),
+                    encode_address(self.address_from),
+                    encode_address(self.address_to),
+                    keccak([amount.as_bytes()]),
+                    keccak([self.asset.as_bytes()]),
+                    keccak([transaction_type.as_bytes()]),
+                    encode_uint(self.nonce as u64),
+                ]);
+
+                keccak([&b"\x19\x01"[..], &domain, &message])
+            }
+        }
+    }
+}
+
+/// Recovers the canonical address that produced `signature` over `digest`.
+/// The recovery byte may be given as 0/1 or 27/28.
+pub fn recover_signer(digest: &[u8; 32], signature: &str) -> Result<String, SignatureError> {
+    let bytes = signature
+        .strip_prefix("0x")
+        .and_then(|digits| hex::decode(digits).ok())
+        .filter(|bytes| bytes.len() == 65)
+        .ok_or(SignatureError::Format)?;
+
+    let rs = Signature::from_slice(&bytes[..64]).map_err(|_| SignatureError::Invalid)?;
+    // Ethereum only accepts the low-s form (EIP-2), so that a valid
+    // signature cannot be turned into a second valid one.
+    if rs.normalize_s().is_some() {
+        return Err(SignatureError::Invalid);
+    }
+    let v = match bytes[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        _ => return Err(SignatureError::Invalid),
+    };
+    let recovery_id = RecoveryId::from_byte(v).ok_or(SignatureError::Invalid)?;
+
+    let key = VerifyingKey::recover_from_prehash(digest, &rs, recovery_id)
+        .map_err(|_| SignatureError::Invalid)?;
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+
+    Ok(format!("0x{}", hex::encode(&hash[12..])))
+}
+
+fn keccak<T: AsRef<[u8]>>(parts: impl IntoIterator<Item = T>) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// ABI encoding of an address: its 20 bytes, left-padded to 32.
+fn encode_address(address: &str) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    if let Some(bytes) = address
+        .strip_prefix("0x")
+        .and_then(|digits| hex::decode(digits).ok())
+        .filter(|bytes| bytes.len() == 20)
+    {
+        word[12..].copy_from_slice(&bytes);
+    }
+    word
+}
+
+fn encode_uint(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    // Key and address from the web3.js `accounts.sign` documentation.
+    const PRIVATE_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ADDRESS: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+    fn sign(digest: &[u8; 32]) -> String {
+        let key = SigningKey::from_slice(&hex::decode(PRIVATE_KEY).unwrap()).unwrap();
+        let (signature, recovery_id) = key.sign_prehash_recoverable(digest).unwrap();
+        format!(
+            "0x{}{:02x}",
+            hex::encode(signature.to_bytes()),
+            27 + recovery_id.to_byte()
+        )
+    }
+
+    fn transaction() -> SignedTransaction<'static> {
+        SignedTransaction {
+            address_from: ADDRESS,
+            address_to: "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            amount: Decimal::new(1050, 2),
+            asset: "ETH",
+            transaction_type: TransactionType::Withdrawal,
+            nonce: 0,
+        }
+    }
+
+    #[test]
+    fn test_recovers_web3_personal_sign_vector() {
+        let message = "Some data";
+        let mut hasher = Keccak256::new();
+        hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()));
+        hasher.update(message);
+        let digest: [u8; 32] = hasher.finalize().into();
+
+        let signature = "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c";
+        assert_eq!(recover_signer(&digest, signature).unwrap(), ADDRESS);
+    }
+
+    #[test]
+    fn test_both_schemes_recover_the_signer() {
+        for scheme in [SignatureScheme::Eip191, SignatureScheme::Eip712] {
+            let digest = transaction().digest(scheme);
+            assert_eq!(recover_signer(&digest, &sign(&digest)).unwrap(), ADDRESS);
+        }
+    }
+
+    #[test]
+    fn test_signature_covers_every_field() {
+        let digest = transaction().digest(SignatureScheme::Eip712);
+        let signature = sign(&digest);
+
+        let tampered = SignedTransaction {
+            nonce: 1,
+            ..transaction()
+        };
+        let recovered =
+            recover_signer(&tampered.digest(SignatureScheme::Eip712), &signature).unwrap();
+        assert_ne!(recovered, ADDRESS);
+
+        assert_eq!(
+            transaction().message(),
+            format!(
+                "Transfer 10.5 ETH from {} to 0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed\nType: Withdrawal\nNonce: 0",
+                ADDRESS
+            )
+        );
+    }
+
+    #[test]
+    fn test_malformed_signatures_are_rejected() {
+        let digest = transaction().digest(SignatureScheme::Eip191);
+        let signature = sign(&digest);
+
+        assert_eq!(
+            recover_signer(&digest, &signature[2..]),
+            Err(SignatureError::Format)
+        );
+        assert_eq!(
+            recover_signer(&digest, &signature[..130]),
+            Err(SignatureError::Format)
+        );
+        assert_eq!(
+            recover_signer(&digest, &format!("{}1f", &signature[..130])),
+            Err(SignatureError::Invalid)
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::modules::transactions::repository::filtered_transactions_query;
+use crate::modules::transactions::request::TransactionFilter;
+use crate::modules::transactions::response::{Transaction, format_timestamp};
+use crate::modules::transactions::{TransactionStatus, TransactionType};
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt, stream};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// Bytes collected before a chunk is sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks buffered between the database and a slow client. Once full,
+/// reading rows waits for the client, which bounds an export's memory.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExportFormat {
+    /// Comma-separated values with a header row.
+    #[default]
+    Csv,
+    /// One JSON transaction per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/jsonl",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Column order of CSV exports.
+const CSV_HEADER: [&str; 12] = [
+    "id",
+    "created_at",
+    "address_from",
+    "address_to",
+    "amount",
+    "asset",
+    "transaction_type",
+    "status",
+    "reversal_of",
+    "reversed_by",
+    "api_key_id",
+    "nonce",
+];
+
+/// A transaction as a CSV row. Amounts are normalized for spreadsheets,
+/// `2.5` rather than the stored `2.500000000000000000`.
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    id: Option<i32>,
+    created_at: Option<String>,
+    address_from: &'a str,
+    address_to: &'a str,
+    amount: Decimal,
+    asset: &'a str,
+    transaction_type: TransactionType,
+    status: TransactionStatus,
+    reversal_of: Option<i32>,
+    reversed_by: Option<i32>,
+    api_key_id: Option<i32>,
+    nonce: Option<i64>,
+}
+
+impl<'a> From<&'a Transaction> for CsvRow<'a> {
+    fn from(transaction: &'a Transaction) -> Self {
+        CsvRow {
+            id: transaction.id,
+            created_at: transaction
+                .created_at
+                .as_ref()
+                .and_then(|date| format_timestamp(date).ok()),
+            address_from: &transaction.address_from,
+            address_to: &transaction.address_to,
+            amount: transaction.amount.normalize(),
+            asset: &transaction.asset,
+            transaction_type: transaction.transaction_type,
+            status: transaction.status,
+            reversal_of: transaction.reversal_of,
+            reversed_by: transaction.reversed_by,
+            api_key_id: transaction.api_key_id,
+            nonce: transaction.nonce,
+        }
+    }
+}
+
+/// Encodes transactions one at a time into an output buffer.
+enum Encoder {
+    Csv(Box<csv::Writer<Vec<u8>>>),
+    Jsonl(Vec<u8>),
+}
+
+impl Encoder {
+    fn new(format: ExportFormat) -> Result<Self, csv::Error> {
+        Ok(match format {
+            ExportFormat::Csv => {
+                let mut writer = csv_writer();
+                writer.write_record(CSV_HEADER)?;
+                Encoder::Csv(writer)
+            }
+            ExportFormat::Jsonl => Encoder::Jsonl(vec![]),
+        })
+    }
+
+    fn push(&mut self, transaction: &Transaction) -> Result<(), csv::Error> {
+        match self {
+            Encoder::Csv(writer) => writer.serialize(CsvRow::from(transaction)),
+            Encoder::Jsonl(buffer) => {
+                serde_json::to_writer(&mut *buffer, transaction).map_err(std::io::Error::from)?;
+                buffer.push(b'\n');
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes the encoded output written so far.
+    fn take(&mut self) -> Result<Vec<u8>, csv::Error> {
+        Ok(match self {
+            Encoder::Csv(writer) => std::mem::replace(writer, csv_writer())
+                .into_inner()
+                .map_err(|e| e.into_error())?,
+            Encoder::Jsonl(buffer) => std::mem::take(buffer),
+        })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Encoder::Csv(writer) => writer.get_ref().len(),
+            Encoder::Jsonl(buffer) => buffer.len(),
+        }
+    }
+}
+
+/// Writes rows only: the header is written once, by [`Encoder::new`].
+fn csv_writer() -> Box<csv::Writer<Vec<u8>>> {
+    Box::new(
+        csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]),
+    )
+}
+
+/// Streams every transaction matching `filter` in `format`, reading rows from
+/// the database as the client consumes them rather than loading them all.
+/// `filter.limit` is ignored. A database error mid-export ends the stream
+/// with an error, which aborts the response.
+pub(crate) fn export_transactions(
+    pool: PgPool,
+    address: Option<String>,
+    filter: TransactionFilter,
+    format: ExportFormat,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+
+    actix_web::rt::spawn(async move {
+        let result: Result<(), Box<dyn std::error::Error>> = async {
+            let mut encoder = Encoder::new(format)?;
+            let mut query = filtered_transactions_query(address.as_deref(), &filter);
+            let mut rows = query.build_query_as::<Transaction>().fetch(&pool);
+
+            while let Some(transaction) = rows.next().await {
+                encoder.push(&transaction?)?;
+                if encoder.len() >= CHUNK_SIZE
+                    && sender.send(Ok(Bytes::from(encoder.take()?))).await.is_err()
+                {
+                    // The client went away.
+                    return Ok(());
+                }
+            }
+
+            let _ = sender.send(Ok(Bytes::from(encoder.take()?))).await;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            error!("Transaction export failed: {}", e);
+            let _ = sender
+                .send(Err(actix_web::error::ErrorInternalServerError(
+                    "Export failed",
+                )))
+                .await;
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+}
@@ -0,0 +1,184 @@
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::modules::metrics::metrics;
+use crate::modules::transactions::repository::validate_and_insert;
+use crate::modules::transactions::request::{CreateTransactionRequest, to_utc_primitive};
+use crate::modules::transactions::response::{Transaction, parse_timestamp};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+/// Largest CSV accepted by the import endpoint.
+pub(crate) const MAX_IMPORT_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ImportQuery {
+    /// Validate the file and report its errors without writing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Outcome of an import. Transactions are written only when every row is
+/// valid and the import is not a dry run.
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Whether the transactions were written.
+    pub committed: bool,
+    /// Data rows in the file, valid or not.
+    pub rows: usize,
+    /// Problems with each invalid row, in file order.
+    pub errors: Vec<RowError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    /// Line of the row in the file, counting the header as line 1.
+    pub line: u64,
+    pub errors: Vec<FieldError>,
+}
+
+/// One parsed row: a transaction request and when it happened.
+struct ImportRow {
+    line: u64,
+    created_at: PrimitiveDateTime,
+    request: CreateTransactionRequest,
+}
+
+/// Imports historical transactions from a CSV file whose header names the
+/// columns of [`CreateTransactionRequest`] plus `created_at`, in RFC 3339 or
+/// in the format of the exports, which can therefore be imported back. Other
+/// columns are ignored; signatures and nonces are not carried over.
+///
+/// Rows are validated in chronological order, each against the balances left
+/// by the transactions before it, and inserted in a single database
+/// transaction that is only committed when no row fails. A dry run makes the
+/// same checks and rolls back.
+pub(crate) async fn import_transactions(
+    pool: &PgPool,
+    csv: &[u8],
+    dry_run: bool,
+) -> Result<ImportReport, ApiError> {
+    let (mut rows, mut errors) = parse_rows(csv).map_err(|e| ApiError::Validation(vec![e]))?;
+    let total = rows.len() + errors.len();
+
+    // Stable, so rows recorded at the same time keep their file order.
+    rows.sort_by_key(|row| row.created_at);
+
+    let mut db_tx = pool.begin().await?;
+    let mut imported = vec![];
+
+    for row in rows {
+        let mut request = row.request;
+        request.normalize_addresses();
+        let mut transaction = Transaction::from(request);
+        transaction.created_at = Some(row.created_at);
+        transaction.nonce = None;
+        let transaction_type = transaction.transaction_type;
+
+        match validate_and_insert(&mut db_tx, transaction).await? {
+            Ok(_) => imported.push(transaction_type),
+            Err(row_errors) => errors.push(RowError {
+                line: row.line,
+                errors: row_errors,
+            }),
+        }
+    }
+
+    errors.sort_by_key(|error| error.line);
+    let committed = !dry_run && errors.is_empty();
+    if committed {
+        db_tx.commit().await?;
+        for transaction_type in imported {
+            metrics().transaction_created(transaction_type);
+        }
+    } else {
+        db_tx.rollback().await?;
+    }
+
+    Ok(ImportReport {
+        dry_run,
+        committed,
+        rows: total,
+        errors,
+    })
+}
+
+/// Parses every row of `csv`, collecting the ones that cannot be read
+/// separately. Fails only when the header is unusable.
+fn parse_rows(csv: &[u8]) -> Result<(Vec<ImportRow>, Vec<RowError>), FieldError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv);
+    let headers = reader
+        .headers()
+        .map_err(|e| FieldError::general(ErrorCode::MalformedRequest, e.to_string()))?
+        .clone();
+    let created_at_column = headers
+        .iter()
+        .position(|header| header == "created_at")
+        .ok_or_else(|| {
+            FieldError::general(
+                ErrorCode::MalformedRequest,
+                "The CSV header has no created_at column.",
+            )
+        })?;
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(RowError {
+                    line: e.position().map(|p| p.line()).unwrap_or_default(),
+                    errors: vec![FieldError::general(
+                        ErrorCode::MalformedRequest,
+                        e.to_string(),
+                    )],
+                });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let mut row_errors = vec![];
+        let created_at = parse_created_at(&record[created_at_column]);
+        if created_at.is_none() {
+            row_errors.push(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "created_at",
+                "created_at must be RFC 3339 or YYYY-MM-DD HH:MM:SS.fff in UTC.",
+            ));
+        }
+        let request = record
+            .deserialize::<CreateTransactionRequest>(Some(&headers))
+            .map_err(|e| {
+                row_errors.push(FieldError::general(
+                    ErrorCode::MalformedRequest,
+                    e.to_string(),
+                ))
+            });
+
+        match (created_at, request) {
+            (Some(created_at), Ok(request)) => rows.push(ImportRow {
+                line,
+                created_at,
+                request,
+            }),
+            _ => errors.push(RowError {
+                line,
+                errors: row_errors,
+            }),
+        }
+    }
+
+    Ok((rows, errors))
+}
+
+fn parse_created_at(value: &str) -> Option<PrimitiveDateTime> {
+    OffsetDateTime::parse(value, &Rfc3339)
+        .map(to_utc_primitive)
+        .or_else(|_| parse_timestamp(value))
+        .ok()
+}
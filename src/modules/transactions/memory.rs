@@ -0,0 +1,342 @@
+use crate::api::error::FieldError;
+use crate::modules::assets::Asset;
+use crate::modules::transactions::TransactionStatus;
+use crate::modules::transactions::pagination::SortOrder;
+use crate::modules::transactions::repository::{
+    IdempotencyKey, InsertOutcome, TransactionRepository, unexpected_nonce,
+};
+use crate::modules::transactions::request::{TransactionFilter, to_utc_primitive};
+use crate::modules::transactions::response::{IdempotentResponse, Transaction};
+use crate::modules::wallet::policy::AccountPolicy;
+use crate::modules::wallet::response::AssetBalance;
+use crate::modules::wallet::services::{calculate_balance, calculate_balances};
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime};
+
+/// [`TransactionRepository`] that keeps everything in memory, so the HTTP API
+/// can be exercised without PostgreSQL. Accounts use the default overdraft
+/// policy unless given another one, and have no owner.
+pub struct InMemoryTransactionRepository {
+    state: Mutex<State>,
+}
+
+struct State {
+    transactions: Vec<Transaction>,
+    idempotency_keys: HashMap<String, (IdempotentResponse, PrimitiveDateTime)>,
+    assets: Vec<Asset>,
+    policies: HashMap<String, AccountPolicy>,
+}
+
+impl InMemoryTransactionRepository {
+    /// An empty repository with the same assets as a fresh database.
+    pub fn new() -> Self {
+        let asset = |symbol: &str, decimals: i16, contract_address: Option<&str>| Asset {
+            symbol: symbol.to_string(),
+            decimals,
+            contract_address: contract_address.map(str::to_string),
+        };
+
+        InMemoryTransactionRepository {
+            state: Mutex::new(State {
+                transactions: vec![],
+                idempotency_keys: HashMap::new(),
+                assets: vec![
+                    asset("ETH", 18, None),
+                    asset(
+                        "USDC",
+                        6,
+                        Some("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                    ),
+                    asset(
+                        "USDT",
+                        6,
+                        Some("0xdac17f958d2ee523a2206206994597c13d831ec7"),
+                    ),
+                    asset(
+                        "DAI",
+                        18,
+                        Some("0x6b175474e89094c44da98b954eedeac495271d0f"),
+                    ),
+                ],
+                policies: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Sets the overdraft policy of `address`, which must be canonical.
+    pub fn set_account_policy(&self, address: &str, policy: AccountPolicy) {
+        let mut state = self.state.lock().unwrap();
+        state.policies.insert(address.to_string(), policy);
+    }
+}
+
+impl State {
+    fn page(&self, address: Option<&str>, filter: &TransactionFilter) -> Vec<Transaction> {
+        let involves = |tx: &Transaction, address: &str| {
+            tx.address_from == address || tx.address_to == address
+        };
+
+        let mut rows: Vec<Transaction> = self
+            .transactions
+            .iter()
+            .filter(|tx| match (address, &filter.counterparty) {
+                (Some(address), Some(counterparty)) => {
+                    (tx.address_from == address && &tx.address_to == counterparty)
+                        || (tx.address_to == address && &tx.address_from == counterparty)
+                }
+                (Some(address), None) => involves(tx, address),
+                (None, Some(counterparty)) => involves(tx, counterparty),
+                (None, None) => true,
+            })
+            .filter(|tx| {
+                filter
+                    .transaction_type
+                    .is_none_or(|t| tx.transaction_type == t)
+            })
+            .filter(|tx| filter.status.is_none_or(|status| tx.status == status))
+            .filter(|tx| filter.min_amount.is_none_or(|min| tx.amount >= min))
+            .filter(|tx| filter.max_amount.is_none_or(|max| tx.amount <= max))
+            .filter(|tx| {
+                filter
+                    .created_from
+                    .is_none_or(|from| tx.created_at.is_some_and(|at| at >= from))
+            })
+            .filter(|tx| {
+                filter
+                    .created_to
+                    .is_none_or(|to| tx.created_at.is_some_and(|at| at <= to))
+            })
+            .filter(|tx| {
+                filter.cursor.is_none_or(|cursor| {
+                    let key = (tx.created_at, tx.id);
+                    let after = (Some(cursor.created_at), Some(cursor.id));
+                    match filter.order {
+                        SortOrder::Asc => key > after,
+                        SortOrder::Desc => key < after,
+                    }
+                })
+            })
+            .cloned()
+            .collect();
+
+        rows.sort_by_key(|tx| (tx.created_at, tx.id));
+        if filter.order == SortOrder::Desc {
+            rows.reverse();
+        }
+        rows.truncate(filter.limit as usize + 1);
+
+        rows
+    }
+
+    fn available_balance(&self, address: &str, asset: &str) -> Decimal {
+        let transactions: Vec<Transaction> = self
+            .transactions
+            .iter()
+            .filter(|tx| tx.asset == asset)
+            .cloned()
+            .collect();
+        let pending: Decimal = transactions
+            .iter()
+            .filter(|tx| tx.address_from == address && tx.status == TransactionStatus::Pending)
+            .map(|tx| tx.amount)
+            .sum();
+
+        calculate_balance(address, &transactions) - pending
+    }
+
+    /// Checks `transaction` like [`PgTransactionRepository`] does and stores
+    /// it when valid.
+    ///
+    /// [`PgTransactionRepository`]: crate::modules::transactions::repository::PgTransactionRepository
+    fn validate_and_push(
+        &mut self,
+        mut transaction: Transaction,
+        now: PrimitiveDateTime,
+    ) -> Result<i32, Vec<FieldError>> {
+        if let Some(nonce) = transaction.nonce {
+            let expected = self.next_nonce(&transaction.address_from);
+            if nonce != expected {
+                return Err(vec![unexpected_nonce(expected)]);
+            }
+        }
+
+        let balance = self.available_balance(&transaction.address_from, &transaction.asset);
+        let asset = self
+            .assets
+            .iter()
+            .find(|asset| asset.symbol == transaction.asset);
+
+        let policy = self
+            .policies
+            .get(&transaction.address_from)
+            .cloned()
+            .unwrap_or_default();
+
+        let errors = transaction.validate(balance, &policy, asset);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let id = self.transactions.len() as i32 + 1;
+        transaction.id = Some(id);
+        transaction.created_at = Some(now);
+        self.transactions.push(transaction);
+
+        Ok(id)
+    }
+
+    fn next_nonce(&self, address: &str) -> i64 {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.address_from == address)
+            .filter_map(|tx| tx.nonce)
+            .max()
+            .map_or(0, |nonce| nonce + 1)
+    }
+}
+
+#[async_trait(?Send)]
+impl TransactionRepository for InMemoryTransactionRepository {
+    async fn list(&self, filter: &TransactionFilter) -> Result<Vec<Transaction>, sqlx::Error> {
+        Ok(self.state.lock().unwrap().page(None, filter))
+    }
+
+    async fn list_by_address(
+        &self,
+        address: &str,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        Ok(self.state.lock().unwrap().page(Some(address), filter))
+    }
+
+    async fn find(&self, id: i32) -> Result<Option<Transaction>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .transactions
+            .iter()
+            .find(|tx| tx.id == Some(id))
+            .cloned())
+    }
+
+    async fn insert(
+        &self,
+        transaction: Transaction,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> Result<InsertOutcome, sqlx::Error> {
+        // Holding the lock for the whole insert serializes it with every
+        // other one, which is what the address and key locks achieve in
+        // PostgreSQL.
+        let mut state = self.state.lock().unwrap();
+        let now = to_utc_primitive(OffsetDateTime::now_utc());
+
+        if let Some(key) = idempotency_key
+            && let Some((stored, created_at)) = state.idempotency_keys.get(&key.key)
+            && *created_at > now - Duration::seconds(key.ttl_seconds)
+        {
+            return Ok(if stored.fingerprint == key.fingerprint {
+                InsertOutcome::Replayed(stored.clone())
+            } else {
+                InsertOutcome::KeyReused
+            });
+        }
+
+        let id = match state.validate_and_push(transaction, now) {
+            Ok(id) => id,
+            Err(errors) => return Ok(InsertOutcome::Rejected(errors)),
+        };
+
+        if let Some(key) = idempotency_key {
+            let response = IdempotentResponse {
+                fingerprint: key.fingerprint.clone(),
+                transaction_id: id,
+                status: StatusCode::CREATED.as_u16() as i16,
+            };
+            state
+                .idempotency_keys
+                .insert(key.key.clone(), (response, now));
+        }
+
+        Ok(InsertOutcome::Created(id))
+    }
+
+    async fn insert_batch(
+        &self,
+        items: Vec<Result<Transaction, Vec<FieldError>>>,
+        atomic: bool,
+    ) -> Result<Vec<Result<i32, Vec<FieldError>>>, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = to_utc_primitive(OffsetDateTime::now_utc());
+        let stored = state.transactions.len();
+
+        let results: Vec<_> = items
+            .into_iter()
+            .map(|item| item.and_then(|transaction| state.validate_and_push(transaction, now)))
+            .collect();
+
+        if atomic && results.iter().any(Result::is_err) {
+            state.transactions.truncate(stored);
+        }
+
+        Ok(results)
+    }
+
+    async fn balances(
+        &self,
+        address: &str,
+        asset: Option<&str>,
+        at: Option<PrimitiveDateTime>,
+        through_id: Option<i32>,
+    ) -> Result<Vec<AssetBalance>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+
+        let transactions: Vec<Transaction> = state
+            .transactions
+            .iter()
+            .filter(|tx| tx.address_from == address || tx.address_to == address)
+            .filter(|tx| {
+                at.is_none_or(|at| match tx.created_at {
+                    Some(created_at) if created_at == at => {
+                        through_id.is_none_or(|through_id| tx.id <= Some(through_id))
+                    }
+                    Some(created_at) => created_at < at,
+                    None => false,
+                })
+            })
+            .cloned()
+            .collect();
+
+        let mut balances = calculate_balances(address, &transactions);
+        if let Some(asset) = asset {
+            balances.retain(|balance| balance.asset == asset);
+            if balances.is_empty() {
+                balances.push(AssetBalance {
+                    asset: asset.to_string(),
+                    balance: Decimal::ZERO,
+                });
+            }
+        }
+
+        Ok(balances)
+    }
+
+    async fn find_asset(&self, symbol: &str) -> Result<Option<Asset>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .assets
+            .iter()
+            .find(|asset| asset.symbol == symbol)
+            .cloned())
+    }
+
+    async fn address_owner(&self, _address: &str) -> Result<Option<i32>, sqlx::Error> {
+        Ok(None)
+    }
+
+    async fn next_nonce(&self, address: &str) -> Result<i64, sqlx::Error> {
+        Ok(self.state.lock().unwrap().next_nonce(address))
+    }
+}
@@ -1,23 +1,103 @@
+use crate::modules::transactions::import::MAX_IMPORT_BYTES;
 use crate::modules::transactions::services::{
-    create_transaction, get_transactions, get_transactions_address,
+    cancel_transaction, confirm_transaction, create_transaction, create_transaction_batch,
+    export_address_transactions, export_all_transactions, fail_transaction, get_next_nonce,
+    get_transactions, get_transactions_address, import_transactions_csv, reverse_transaction,
 };
 use actix_web::web;
 use serde::{Deserialize, Serialize};
 
+mod export;
+pub mod import;
+pub mod pagination;
 pub mod repository;
-mod request;
+pub mod request;
 pub mod response;
 mod services;
 
+#[cfg(test)]
+pub mod memory;
+#[cfg(test)]
+pub mod test;
+
 pub fn api_config(cfg: &mut web::ServiceConfig) {
+    // Exports are registered first so that `export` is not taken for an address.
     cfg.service(get_transactions)
+        .service(export_all_transactions)
+        .service(export_address_transactions)
         .service(get_transactions_address)
-        .service(create_transaction);
+        .service(get_next_nonce)
+        .service(create_transaction)
+        .service(create_transaction_batch)
+        .service(reverse_transaction)
+        .service(confirm_transaction)
+        .service(cancel_transaction)
+        .service(fail_transaction);
+}
+
+/// The import endpoint, which bypasses ownership checks and is mounted on
+/// its own admin-only scope.
+pub fn import_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .app_data(web::PayloadConfig::new(MAX_IMPORT_BYTES))
+            .route(web::post().to(import_transactions_csv)),
+    );
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "VARCHAR")]
 pub enum TransactionType {
     Deposit,
     Withdrawal,
 }
+
+/// Lifecycle of a transaction. Only confirmed transactions are posted to the
+/// ledger; the other states are either still open or final without effect.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionStatus {
+    Pending,
+    #[default]
+    Confirmed,
+    Failed,
+    Cancelled,
+}
+
+impl TransactionStatus {
+    /// Only pending transactions can change state.
+    pub fn can_transition_to(&self, next: TransactionStatus) -> bool {
+        matches!(
+            (self, next),
+            (
+                TransactionStatus::Pending,
+                TransactionStatus::Confirmed
+                    | TransactionStatus::Failed
+                    | TransactionStatus::Cancelled
+            )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransactionStatus::*;
+
+    #[test]
+    fn test_pending_can_be_settled() {
+        assert!(Pending.can_transition_to(Confirmed));
+        assert!(Pending.can_transition_to(Failed));
+        assert!(Pending.can_transition_to(Cancelled));
+        assert!(!Pending.can_transition_to(Pending));
+    }
+
+    #[test]
+    fn test_settled_states_are_final() {
+        for from in [Confirmed, Failed, Cancelled] {
+            for to in [Pending, Confirmed, Failed, Cancelled] {
+                assert!(!from.can_transition_to(to));
+            }
+        }
+    }
+}
@@ -1,28 +1,588 @@
-use crate::modules::transactions::response::Transaction;
-use sqlx::PgPool;
-use std::error::Error;
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::assets::Asset;
+use crate::modules::assets::repository::find_asset;
+use crate::modules::transactions::TransactionStatus;
+use crate::modules::transactions::request::TransactionFilter;
+use crate::modules::transactions::response::{IdempotentResponse, Transaction};
+use crate::modules::users::repository::get_address_owner;
+use crate::modules::wallet::repository::{
+    get_account_policy, get_available_balance, get_stored_balance, get_stored_balances,
+};
+use crate::modules::wallet::response::AssetBalance;
+use crate::modules::wallet::services::calculate_balances;
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
+use time::PrimitiveDateTime;
 
-pub(crate) async fn get_all_transactions(
+/// An `Idempotency-Key` together with the fingerprint of the request that
+/// carried it and how long its response is kept.
+#[derive(Debug, Clone)]
+pub struct IdempotencyKey {
+    pub key: String,
+    pub fingerprint: String,
+    pub ttl_seconds: i64,
+}
+
+/// Result of [`TransactionRepository::insert`].
+#[derive(Debug)]
+pub enum InsertOutcome {
+    Created(i32),
+    /// The key was already used for the same request; its response is replayed.
+    Replayed(IdempotentResponse),
+    /// The key was already used for a different request.
+    KeyReused,
+    /// The transaction failed validation and nothing was written.
+    Rejected(Vec<FieldError>),
+}
+
+/// Storage behind the transaction listing, creation and balance endpoints.
+/// Handlers receive it as `web::Data<dyn TransactionRepository>`.
+#[async_trait(?Send)]
+pub trait TransactionRepository: Send + Sync {
+    /// Up to `filter.limit + 1` transactions in `(created_at, id)` order, so
+    /// the caller can tell whether another page follows.
+    async fn list(&self, filter: &TransactionFilter) -> Result<Vec<Transaction>, sqlx::Error>;
+
+    /// Like [`TransactionRepository::list`], restricted to transactions
+    /// involving `address`.
+    async fn list_by_address(
+        &self,
+        address: &str,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, sqlx::Error>;
+
+    async fn find(&self, id: i32) -> Result<Option<Transaction>, sqlx::Error>;
+
+    /// Validates `transaction` against the available balance of its source
+    /// and stores it, atomically with respect to other inserts from the same
+    /// address and requests carrying the same idempotency key.
+    async fn insert(
+        &self,
+        transaction: Transaction,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> Result<InsertOutcome, sqlx::Error>;
+
+    /// Validates and inserts the items in order, each against the balances
+    /// left by the ones before it. Items the caller already rejected are
+    /// passed as `Err` and kept as they are. When `atomic`, nothing is written
+    /// unless every item is valid; otherwise every valid item is. Returns the
+    /// id or the errors of each item; the ids of an atomic batch that was
+    /// rolled back were never committed.
+    async fn insert_batch(
+        &self,
+        items: Vec<Result<Transaction, Vec<FieldError>>>,
+        atomic: bool,
+    ) -> Result<Vec<Result<i32, Vec<FieldError>>>, sqlx::Error>;
+
+    /// Balances of `address`, in `asset` only when given. With `at`, only
+    /// transactions recorded up to then count, and `through_id` breaks ties
+    /// between transactions recorded at exactly `at`.
+    async fn balances(
+        &self,
+        address: &str,
+        asset: Option<&str>,
+        at: Option<PrimitiveDateTime>,
+        through_id: Option<i32>,
+    ) -> Result<Vec<AssetBalance>, sqlx::Error>;
+
+    async fn find_asset(&self, symbol: &str) -> Result<Option<Asset>, sqlx::Error>;
+
+    /// The user owning `address`, or `None` when nobody does.
+    async fn address_owner(&self, address: &str) -> Result<Option<i32>, sqlx::Error>;
+
+    /// The nonce the next signed request from `address` must carry: one past
+    /// the highest used so far, starting at 0.
+    async fn next_nonce(&self, address: &str) -> Result<i64, sqlx::Error>;
+}
+
+/// [`TransactionRepository`] backed by PostgreSQL.
+pub struct PgTransactionRepository {
+    pool: PgPool,
+}
+
+impl PgTransactionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgTransactionRepository { pool }
+    }
+}
+
+#[async_trait(?Send)]
+impl TransactionRepository for PgTransactionRepository {
+    async fn list(&self, filter: &TransactionFilter) -> Result<Vec<Transaction>, sqlx::Error> {
+        get_transactions_page(&self.pool, None, filter).await
+    }
+
+    async fn list_by_address(
+        &self,
+        address: &str,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        get_transactions_page(&self.pool, Some(address), filter).await
+    }
+
+    async fn find(&self, id: i32) -> Result<Option<Transaction>, sqlx::Error> {
+        get_transaction(&self.pool, id).await
+    }
+
+    async fn insert(
+        &self,
+        transaction: Transaction,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> Result<InsertOutcome, sqlx::Error> {
+        let mut db_tx = self.pool.begin().await?;
+
+        if let Some(key) = idempotency_key {
+            // Taken before the address lock so that retries of the same
+            // request wait for the first attempt instead of racing it.
+            lock_idempotency_key(&mut *db_tx, &key.key).await?;
+
+            match find_idempotent_response(&mut *db_tx, &key.key, key.ttl_seconds).await? {
+                Some(stored) if stored.fingerprint == key.fingerprint => {
+                    return Ok(InsertOutcome::Replayed(stored));
+                }
+                Some(_) => return Ok(InsertOutcome::KeyReused),
+                None => {}
+            }
+        }
+
+        let id = match validate_and_insert(&mut db_tx, transaction).await? {
+            Ok(id) => id,
+            Err(errors) => return Ok(InsertOutcome::Rejected(errors)),
+        };
+
+        if let Some(key) = idempotency_key {
+            let response = IdempotentResponse {
+                fingerprint: key.fingerprint.clone(),
+                transaction_id: id,
+                status: StatusCode::CREATED.as_u16() as i16,
+            };
+            save_idempotent_response(&mut *db_tx, &key.key, &response).await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(InsertOutcome::Created(id))
+    }
+
+    async fn insert_batch(
+        &self,
+        items: Vec<Result<Transaction, Vec<FieldError>>>,
+        atomic: bool,
+    ) -> Result<Vec<Result<i32, Vec<FieldError>>>, sqlx::Error> {
+        let mut db_tx = self.pool.begin().await?;
+
+        // Every source address is locked up front, in a fixed order, so that
+        // batches sharing addresses cannot deadlock each other.
+        let mut addresses: Vec<String> = items
+            .iter()
+            .flatten()
+            .map(|transaction| transaction.address_from.clone())
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+        for address in &addresses {
+            lock_address(&mut *db_tx, address).await?;
+        }
+
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            results.push(match item {
+                Ok(transaction) => validate_and_insert(&mut db_tx, transaction).await?,
+                Err(errors) => Err(errors),
+            });
+        }
+
+        if atomic && results.iter().any(Result::is_err) {
+            db_tx.rollback().await?;
+        } else {
+            db_tx.commit().await?;
+        }
+
+        Ok(results)
+    }
+
+    async fn balances(
+        &self,
+        address: &str,
+        asset: Option<&str>,
+        at: Option<PrimitiveDateTime>,
+        through_id: Option<i32>,
+    ) -> Result<Vec<AssetBalance>, sqlx::Error> {
+        let balances = match (at, asset) {
+            (Some(at), _) => {
+                let transactions =
+                    get_transactions_by_address_until(&self.pool, address, at, through_id).await?;
+                calculate_balances(address, &transactions)
+            }
+            (None, Some(asset)) => vec![AssetBalance {
+                asset: asset.to_string(),
+                balance: get_stored_balance(&self.pool, address, asset)
+                    .await?
+                    .unwrap_or(Decimal::ZERO),
+            }],
+            (None, None) => get_stored_balances(&self.pool, address).await?,
+        };
+
+        Ok(balances
+            .into_iter()
+            .filter(|balance| asset.is_none_or(|asset| balance.asset == asset))
+            .collect())
+    }
+
+    async fn find_asset(&self, symbol: &str) -> Result<Option<Asset>, sqlx::Error> {
+        find_asset(&self.pool, symbol).await
+    }
+
+    async fn address_owner(&self, address: &str) -> Result<Option<i32>, sqlx::Error> {
+        get_address_owner(&self.pool, address).await
+    }
+
+    async fn next_nonce(&self, address: &str) -> Result<i64, sqlx::Error> {
+        get_next_nonce(&self.pool, address).await
+    }
+}
+
+/// Rejects a signed request whose nonce is not the `expected` one, because
+/// it was already used or skips ahead.
+pub(crate) fn unexpected_nonce(expected: i64) -> FieldError {
+    FieldError::new(
+        ErrorCode::InvalidNonce,
+        "nonce",
+        format!("Expected nonce {}.", expected),
+    )
+}
+
+/// Checks `transaction` against the nonce, available balance, policy and
+/// asset of its source address, and inserts it when valid. Concurrent
+/// withdrawals from the same address must not both pass the balance check,
+/// so the read-validate-insert sequence runs under the address lock.
+pub(crate) async fn validate_and_insert(
+    conn: &mut PgConnection,
+    transaction: Transaction,
+) -> Result<Result<i32, Vec<FieldError>>, sqlx::Error> {
+    lock_address(&mut *conn, &transaction.address_from).await?;
+
+    if let Some(nonce) = transaction.nonce {
+        let expected = get_next_nonce(&mut *conn, &transaction.address_from).await?;
+        if nonce != expected {
+            return Ok(Err(vec![unexpected_nonce(expected)]));
+        }
+    }
+
+    let balance =
+        get_available_balance(&mut *conn, &transaction.address_from, &transaction.asset).await?;
+    let policy = get_account_policy(&mut *conn, &transaction.address_from)
+        .await?
+        .unwrap_or_default();
+    let asset = find_asset(&mut *conn, &transaction.asset).await?;
+
+    let errors = transaction.validate(balance, &policy, asset.as_ref());
+    if !errors.is_empty() {
+        return Ok(Err(errors));
+    }
+
+    Ok(Ok(transaction.insert(conn).await?))
+}
+
+/// Returns up to `filter.limit + 1` transactions ordered by `(created_at, id)`,
+/// so the caller can tell whether another page follows.
+pub(crate) async fn get_transactions_page(
     pool: &PgPool,
-) -> Result<Vec<Transaction>, Box<dyn Error>> {
-    let transactions = sqlx::query_as::<_, Transaction>("SELECT * FROM transactions")
+    address: Option<&str>,
+    filter: &TransactionFilter,
+) -> Result<Vec<Transaction>, sqlx::Error> {
+    let mut query = filtered_transactions_query(address, filter);
+    query.push(" LIMIT ").push_bind(filter.limit + 1);
+
+    let transactions = query
+        .build_query_as::<Transaction>()
         .fetch_all(pool)
         .await?;
 
     Ok(transactions)
 }
 
-pub async fn get_transactions_by_address(
-    pool: &PgPool,
+/// Selects every transaction matching `filter` after its cursor, ordered by
+/// `(created_at, id)`, without a limit. When `address` is given, only
+/// transactions involving it are selected and `counterparty` matches the
+/// other side of the transfer.
+pub(crate) fn filtered_transactions_query<'a>(
+    address: Option<&'a str>,
+    filter: &'a TransactionFilter,
+) -> QueryBuilder<'a, Postgres> {
+    let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM transactions WHERE TRUE");
+
+    match (address, &filter.counterparty) {
+        (Some(address), Some(counterparty)) => {
+            query
+                .push(" AND ((address_from = ")
+                .push_bind(address)
+                .push(" AND address_to = ")
+                .push_bind(counterparty)
+                .push(") OR (address_to = ")
+                .push_bind(address)
+                .push(" AND address_from = ")
+                .push_bind(counterparty)
+                .push("))");
+        }
+        (Some(address), None) => {
+            query
+                .push(" AND (address_from = ")
+                .push_bind(address)
+                .push(" OR address_to = ")
+                .push_bind(address)
+                .push(")");
+        }
+        (None, Some(counterparty)) => {
+            query
+                .push(" AND (address_from = ")
+                .push_bind(counterparty)
+                .push(" OR address_to = ")
+                .push_bind(counterparty)
+                .push(")");
+        }
+        (None, None) => {}
+    }
+
+    if let Some(transaction_type) = &filter.transaction_type {
+        query.push(" AND type = ").push_bind(transaction_type);
+    }
+    if let Some(status) = filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(min_amount) = filter.min_amount {
+        query.push(" AND amount >= ").push_bind(min_amount);
+    }
+    if let Some(max_amount) = filter.max_amount {
+        query.push(" AND amount <= ").push_bind(max_amount);
+    }
+    if let Some(created_from) = filter.created_from {
+        query.push(" AND created_at >= ").push_bind(created_from);
+    }
+    if let Some(created_to) = filter.created_to {
+        query.push(" AND created_at <= ").push_bind(created_to);
+    }
+    if let Some(cursor) = &filter.cursor {
+        query
+            .push(" AND (created_at, id) ")
+            .push(filter.order.after_operator())
+            .push(" (")
+            .push_bind(cursor.created_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    let order = filter.order.as_sql();
+    query.push(format!(" ORDER BY created_at {order}, id {order}"));
+
+    query
+}
+
+pub async fn get_transactions_by_address<'e, E>(
+    executor: E,
     address: &str,
-) -> Result<Vec<Transaction>, Box<dyn Error>> {
+) -> Result<Vec<Transaction>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
     let transactions = sqlx::query_as::<_, Transaction>(
         "SELECT * FROM transactions
          WHERE address_from = $1 OR address_to = $1",
     )
     .bind(address)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(transactions)
+}
+
+/// Transactions involving `address` recorded up to `at`, in `(created_at, id)`
+/// order. When `through_id` is given, transactions sharing the `at` timestamp
+/// are only included up to that id.
+pub async fn get_transactions_by_address_until(
+    pool: &PgPool,
+    address: &str,
+    at: PrimitiveDateTime,
+    through_id: Option<i32>,
+) -> Result<Vec<Transaction>, sqlx::Error> {
+    let transactions = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions
+         WHERE (address_from = $1 OR address_to = $1)
+           AND (created_at < $2 OR (created_at = $2 AND ($3::INTEGER IS NULL OR id <= $3)))
+         ORDER BY created_at, id",
+    )
+    .bind(address)
+    .bind(at)
+    .bind(through_id)
     .fetch_all(pool)
     .await?;
 
     Ok(transactions)
 }
+
+pub(crate) async fn get_next_nonce<'e, E>(executor: E, address: &str) -> Result<i64, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let nonce = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(nonce) + 1, 0) FROM transactions WHERE address_from = $1",
+    )
+    .bind(address)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(nonce)
+}
+
+pub(crate) async fn get_transaction(
+    pool: &PgPool,
+    id: i32,
+) -> Result<Option<Transaction>, sqlx::Error> {
+    let transaction = sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(transaction)
+}
+
+/// Loads transaction `id`, locking its row until the surrounding database
+/// transaction ends.
+pub(crate) async fn get_transaction_for_update<'e, E>(
+    executor: E,
+    id: i32,
+) -> Result<Option<Transaction>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let transaction =
+        sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(transaction)
+}
+
+/// Links transaction `id` to the transaction that reverses it.
+pub(crate) async fn set_reversed_by<'e, E>(
+    executor: E,
+    id: i32,
+    reversal_id: i32,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("UPDATE transactions SET reversed_by = $2 WHERE id = $1")
+        .bind(id)
+        .bind(reversal_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Records that transaction `id` moved to `status` at the request of API key
+/// `api_key_id`.
+pub(crate) async fn set_transaction_status<'e, E>(
+    executor: E,
+    id: i32,
+    status: TransactionStatus,
+    api_key_id: Option<i32>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("UPDATE transactions SET status = $2, settled_by_api_key_id = $3 WHERE id = $1")
+        .bind(id)
+        .bind(status)
+        .bind(api_key_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Serializes balance-changing writes for `address` until the surrounding
+/// database transaction commits or rolls back.
+///
+/// Advisory locks are namespaced by their first key: 1 for addresses, 2 for
+/// idempotency keys.
+pub(crate) async fn lock_address<'e, E>(executor: E, address: &str) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("SELECT pg_advisory_xact_lock(1, hashtext($1))")
+        .bind(address)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Serializes requests carrying the same `Idempotency-Key` until the
+/// surrounding database transaction ends.
+pub(crate) async fn lock_idempotency_key<'e, E>(executor: E, key: &str) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("SELECT pg_advisory_xact_lock(2, hashtext($1))")
+        .bind(key)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the response stored for `key`, ignoring entries older than
+/// `ttl_seconds`.
+pub(crate) async fn find_idempotent_response<'e, E>(
+    executor: E,
+    key: &str,
+    ttl_seconds: i64,
+) -> Result<Option<IdempotentResponse>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let response = sqlx::query_as::<_, IdempotentResponse>(
+        "SELECT fingerprint, transaction_id, status FROM idempotency_keys
+         WHERE key = $1 AND created_at > NOW() - make_interval(secs => $2)",
+    )
+    .bind(key)
+    .bind(ttl_seconds as f64)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(response)
+}
+
+/// Stores the response for `key`, replacing an expired entry if there is one.
+pub(crate) async fn save_idempotent_response<'e, E>(
+    executor: E,
+    key: &str,
+    response: &IdempotentResponse,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "INSERT INTO idempotency_keys (key, fingerprint, transaction_id, status)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (key) DO UPDATE
+         SET fingerprint = EXCLUDED.fingerprint,
+             transaction_id = EXCLUDED.transaction_id,
+             status = EXCLUDED.status,
+             created_at = NOW()",
+    )
+    .bind(key)
+    .bind(&response.fingerprint)
+    .bind(response.transaction_id)
+    .bind(response.status)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
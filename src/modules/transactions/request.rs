@@ -1,11 +1,256 @@
-use crate::modules::transactions::TransactionType;
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::assets::DEFAULT_ASSET;
+use crate::modules::signature::{
+    SignatureError, SignatureScheme, SignedTransaction, recover_signer,
+};
+use crate::modules::transactions::pagination::{
+    Cursor, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, SortOrder,
+};
+use crate::modules::transactions::{TransactionStatus, TransactionType};
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct CreateTransactionRequest {
     pub address_from: String,
     pub address_to: String,
     pub amount: Decimal,
+    #[serde(default = "default_asset")]
+    pub asset: String,
     pub transaction_type: TransactionType,
+    #[serde(default)]
+    pub status: TransactionStatus,
+    /// Signature of the request by `address_from`, as `0x` and 65 hex bytes.
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub signature_scheme: SignatureScheme,
+    /// Required with a signature; see [`TransactionRepository::next_nonce`].
+    ///
+    /// [`TransactionRepository::next_nonce`]: crate::modules::transactions::repository::TransactionRepository::next_nonce
+    pub nonce: Option<i64>,
+}
+
+pub(crate) fn default_asset() -> String {
+    DEFAULT_ASSET.to_string()
+}
+
+impl CreateTransactionRequest {
+    /// Rewrites valid addresses in canonical form. Invalid ones are left as
+    /// sent so that validation can report them.
+    pub fn normalize_addresses(&mut self) {
+        for address in [&mut self.address_from, &mut self.address_to] {
+            if let Ok(canonical) = address::normalize(address) {
+                *address = canonical;
+            }
+        }
+    }
+
+    /// Checks the signature, if any, and returns whether the request is
+    /// signed. A signature must come with a nonce and be made by
+    /// `address_from`, whose addresses must already be normalized.
+    pub fn verify_signature(&self) -> Result<bool, FieldError> {
+        let (signature, nonce) = match (&self.signature, self.nonce) {
+            (None, None) => return Ok(false),
+            (None, Some(_)) => {
+                return Err(FieldError::new(
+                    ErrorCode::InvalidNonce,
+                    "nonce",
+                    "A nonce is only accepted with a signature.",
+                ));
+            }
+            (Some(_), None) => {
+                return Err(FieldError::new(
+                    ErrorCode::InvalidNonce,
+                    "nonce",
+                    "A signed request requires a nonce.",
+                ));
+            }
+            (Some(signature), Some(nonce)) => (signature, nonce),
+        };
+        if nonce < 0 {
+            return Err(FieldError::new(
+                ErrorCode::InvalidNonce,
+                "nonce",
+                "Nonce cannot be negative.",
+            ));
+        }
+
+        let digest = SignedTransaction {
+            address_from: &self.address_from,
+            address_to: &self.address_to,
+            amount: self.amount,
+            asset: &self.asset,
+            transaction_type: self.transaction_type,
+            nonce,
+        }
+        .digest(self.signature_scheme);
+
+        match recover_signer(&digest, signature) {
+            Ok(signer) if signer == self.address_from => Ok(true),
+            Ok(_) => Err(FieldError::new(
+                ErrorCode::InvalidSignature,
+                "signature",
+                "Signature was not made by address_from.",
+            )),
+            Err(SignatureError::Format) => Err(FieldError::new(
+                ErrorCode::InvalidSignature,
+                "signature",
+                "Signature must be 0x followed by 65 hex-encoded bytes.",
+            )),
+            Err(SignatureError::Invalid) => Err(FieldError::new(
+                ErrorCode::InvalidSignature,
+                "signature",
+                "Invalid signature.",
+            )),
+        }
+    }
+
+    /// Hex-encoded SHA-256 of the request fields, used to detect an
+    /// `Idempotency-Key` being reused for a different request. The amount is
+    /// normalized so that `10` and `10.00` produce the same fingerprint.
+    pub fn fingerprint(&self) -> String {
+        let mut canonical = format!(
+            "{}|{}|{}|{}|{:?}|{:?}",
+            self.address_from,
+            self.address_to,
+            self.amount.normalize(),
+            self.asset,
+            self.transaction_type,
+            self.status
+        );
+        if let Some(nonce) = self.nonce {
+            canonical.push_str(&format!("|{}", nonce));
+        }
+        hex::encode(Sha256::digest(canonical.as_bytes()))
+    }
+}
+
+/// Most transactions accepted in one batch.
+pub(crate) const MAX_BATCH_SIZE: usize = 500;
+
+/// How a batch treats invalid items.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Nothing is written unless every item is valid.
+    #[default]
+    Atomic,
+    /// Every valid item is written.
+    BestEffort,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct BatchQuery {
+    #[serde(default)]
+    pub mode: BatchMode,
+}
+
+/// Query string accepted by the transaction listings. Timestamps are RFC 3339
+/// and are compared against `created_at` in UTC.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ListTransactionsQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    #[serde(rename = "type")]
+    pub transaction_type: Option<TransactionType>,
+    pub status: Option<TransactionStatus>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_to: Option<OffsetDateTime>,
+    pub counterparty: Option<String>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// Validated form of [`ListTransactionsQuery`].
+#[derive(Debug)]
+pub(crate) struct TransactionFilter {
+    pub limit: i64,
+    pub cursor: Option<Cursor>,
+    pub transaction_type: Option<TransactionType>,
+    pub status: Option<TransactionStatus>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub created_from: Option<PrimitiveDateTime>,
+    pub created_to: Option<PrimitiveDateTime>,
+    pub counterparty: Option<String>,
+    pub order: SortOrder,
+}
+
+impl ListTransactionsQuery {
+    pub fn into_filter(self) -> Result<TransactionFilter, Vec<FieldError>> {
+        let mut errors = vec![];
+
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            errors.push(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "limit",
+                format!("Limit must be between 1 and {}.", MAX_PAGE_SIZE),
+            ));
+        }
+
+        let cursor = match self.cursor.as_deref().map(Cursor::decode) {
+            Some(None) => {
+                errors.push(FieldError::new(
+                    ErrorCode::InvalidParameter,
+                    "cursor",
+                    "Invalid cursor.",
+                ));
+                None
+            }
+            Some(cursor) => cursor,
+            None => None,
+        };
+
+        if let (Some(min), Some(max)) = (self.min_amount, self.max_amount)
+            && min > max
+        {
+            errors.push(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "min_amount",
+                "min_amount cannot be greater than max_amount.",
+            ));
+        }
+
+        if let (Some(from), Some(to)) = (self.created_from, self.created_to)
+            && from > to
+        {
+            errors.push(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "created_from",
+                "created_from cannot be later than created_to.",
+            ));
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(TransactionFilter {
+            limit,
+            cursor,
+            transaction_type: self.transaction_type,
+            status: self.status,
+            min_amount: self.min_amount,
+            max_amount: self.max_amount,
+            created_from: self.created_from.map(to_utc_primitive),
+            created_to: self.created_to.map(to_utc_primitive),
+            counterparty: self
+                .counterparty
+                .map(|counterparty| address::canonical(&counterparty)),
+            order: self.order,
+        })
+    }
+}
+
+pub(crate) fn to_utc_primitive(date: OffsetDateTime) -> PrimitiveDateTime {
+    let date = date.to_offset(UtcOffset::UTC);
+    PrimitiveDateTime::new(date.date(), date.time())
 }
@@ -1,20 +1,28 @@
-use crate::modules::transactions::TransactionType;
-use crate::modules::transactions::request::CreateTransactionRequest;
-use crate::modules::wallet::services::calculate_balance;
-use regex::Regex;
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::assets::Asset;
+use crate::modules::ledger::Posting;
+use crate::modules::ledger::repository::insert_postings;
+use crate::modules::transactions::pagination::Cursor;
+use crate::modules::transactions::request::{BatchMode, CreateTransactionRequest};
+use crate::modules::transactions::{TransactionStatus, TransactionType};
+use crate::modules::wallet::policy::AccountPolicy;
+use crate::modules::webhooks::EventType;
+use crate::modules::webhooks::repository::insert_outbox_event;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize, Serializer};
-use sqlx::{FromRow, PgPool};
-use std::error::Error;
+use serde_json::json;
+use sqlx::{FromRow, PgConnection};
 use time::{PrimitiveDateTime, format_description};
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Transaction {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i32>,
     pub address_from: String,
     pub address_to: String,
     pub amount: Decimal,
+    pub asset: String,
     #[sqlx(rename = "type")]
     pub transaction_type: TransactionType,
     #[serde(
@@ -22,48 +30,241 @@ pub struct Transaction {
         serialize_with = "serialize_primitive_date"
     )]
     pub created_at: Option<PrimitiveDateTime>,
+    /// Id of the transaction this one reverses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversal_of: Option<i32>,
+    /// Id of the transaction that reverses this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversed_by: Option<i32>,
+    pub status: TransactionStatus,
+    /// Id of the API key the transaction was created with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<i32>,
+    /// Nonce of the signed request that created the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    /// Id of the API key that confirmed, cancelled or failed the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settled_by_api_key_id: Option<i32>,
+}
+
+/// One page of a transaction listing. `next_cursor` is `null` on the last page.
+#[derive(Debug, Serialize)]
+pub struct TransactionPage {
+    pub data: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+}
+
+impl TransactionPage {
+    /// Builds a page from rows fetched with one extra row beyond `limit`.
+    pub fn from_rows(mut rows: Vec<Transaction>, limit: i64) -> Self {
+        let next_cursor = if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+            rows.last().and_then(|tx| {
+                Some(
+                    Cursor {
+                        created_at: tx.created_at?,
+                        id: tx.id?,
+                    }
+                    .encode(),
+                )
+            })
+        } else {
+            None
+        };
+
+        TransactionPage {
+            data: rows,
+            next_cursor,
+        }
+    }
+}
+
+/// The nonce the next signed request from `address` must carry.
+#[derive(Debug, Serialize)]
+pub struct NextNonce {
+    pub address: String,
+    pub nonce: i64,
+}
+
+/// Outcome of a batch, with one result per item in request order.
+#[derive(Debug, Serialize)]
+pub struct BatchResponse {
+    pub mode: BatchMode,
+    pub results: Vec<BatchItemResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub status: BatchItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchItemStatus {
+    Created,
+    Rejected,
+    /// Valid, but not written because another item of an atomic batch was
+    /// rejected.
+    Skipped,
+}
+
+/// Response stored for an `Idempotency-Key` so that retries can be replayed.
+#[derive(Debug, Clone, FromRow)]
+pub struct IdempotentResponse {
+    pub fingerprint: String,
+    pub transaction_id: i32,
+    pub status: i16,
 }
 
 impl Transaction {
-    pub async fn insert(self, pool: &PgPool) -> Result<i32, Box<dyn Error>> {
-        let id: i32 = sqlx::query_scalar(
-            "INSERT INTO transactions (address_from, address_to, amount, type)
-             VALUES ($1, $2, $3, $4)
-             RETURNING id",
+    /// Inserts the transaction, together with its ledger postings when it is
+    /// already confirmed and the webhook event announcing it. Must run inside
+    /// a database transaction so all of them are written atomically.
+    /// `created_at` defaults to now; it is only set for imported history.
+    pub async fn insert(mut self, conn: &mut PgConnection) -> Result<i32, sqlx::Error> {
+        let (id, created_at): (i32, PrimitiveDateTime) = sqlx::query_as(
+            "INSERT INTO transactions
+                 (address_from, address_to, amount, asset, type, reversal_of, status, api_key_id,
+                  nonce, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, COALESCE($10, NOW()))
+             RETURNING id, created_at",
         )
         .bind(&self.address_from)
         .bind(&self.address_to)
         .bind(self.amount)
-        .bind(&self.transaction_type)
-        .fetch_one(pool)
+        .bind(&self.asset)
+        .bind(self.transaction_type)
+        .bind(self.reversal_of)
+        .bind(self.status)
+        .bind(self.api_key_id)
+        .bind(self.nonce)
+        .bind(self.created_at)
+        .fetch_one(&mut *conn)
         .await?;
 
+        if self.status == TransactionStatus::Confirmed {
+            insert_postings(&mut *conn, id, &self.postings()).await?;
+        }
+
+        self.id = Some(id);
+        self.created_at = Some(created_at);
+        self.insert_event(conn, id).await?;
+
         Ok(id)
     }
 
-    pub fn validate(&self, previous_transactions: &[Transaction]) -> Vec<String> {
+    /// Writes transaction `id`, as it now stands, to the webhook outbox, so
+    /// subscribers hear of its creation and of every later status change.
+    pub async fn insert_event(&self, conn: &mut PgConnection, id: i32) -> Result<(), sqlx::Error> {
+        let event_type = EventType::from(self.transaction_type);
+        let payload = json!({ "type": event_type, "transaction": self }).to_string();
+        insert_outbox_event(conn, id, event_type, &payload).await
+    }
+
+    /// The compensating transaction that sends the amount back from
+    /// `address_to` to `address_from`.
+    pub fn reversal(&self) -> Transaction {
+        Transaction {
+            id: None,
+            address_from: self.address_to.clone(),
+            address_to: self.address_from.clone(),
+            amount: self.amount,
+            asset: self.asset.clone(),
+            transaction_type: match self.transaction_type {
+                TransactionType::Deposit => TransactionType::Withdrawal,
+                TransactionType::Withdrawal => TransactionType::Deposit,
+            },
+            created_at: None,
+            reversal_of: self.id,
+            reversed_by: None,
+            status: TransactionStatus::Confirmed,
+            api_key_id: None,
+            nonce: None,
+            settled_by_api_key_id: None,
+        }
+    }
+
+    /// The balanced journal entry for this transaction: whatever its type, the
+    /// amount leaves `address_from` and arrives at `address_to`.
+    pub fn postings(&self) -> Vec<Posting> {
+        vec![
+            Posting::debit(&self.address_from, &self.asset, self.amount),
+            Posting::credit(&self.address_to, &self.asset, self.amount),
+        ]
+    }
+
+    /// Validates the transaction given the available balance and overdraft
+    /// policy of `address_from`, and the precision rules of `asset`, which is
+    /// `None` when the asset is not registered. Every transaction debits
+    /// `address_from`, so the policy applies whatever its type; deposits from
+    /// outside the system come from `unlimited` issuer accounts.
+    pub fn validate(
+        &self,
+        balance: Decimal,
+        policy: &AccountPolicy,
+        asset: Option<&Asset>,
+    ) -> Vec<FieldError> {
         let mut result = vec![];
 
-        let balance = calculate_balance(&self.address_from, previous_transactions);
+        let source = address::normalize(&self.address_from);
+        let destination = address::normalize(&self.address_to);
+        let valid_source = source.is_ok();
 
-        if !previous_transactions.is_empty() && self.amount > balance {
-            result.push("Insufficient balance".to_string());
+        if valid_source && !policy.allows(balance, self.amount) {
+            result.push(FieldError::new(
+                ErrorCode::InsufficientBalance,
+                "amount",
+                "Insufficient balance",
+            ));
         }
 
-        if self.address_from == self.address_to {
-            result.push("Source and destination addresses cannot be the same.".to_string());
+        if address::canonical(&self.address_from) == address::canonical(&self.address_to) {
+            result.push(FieldError::new(
+                ErrorCode::SameAddress,
+                "address_to",
+                "Source and destination addresses cannot be the same.",
+            ));
         }
 
-        let address_regex = Regex::new(r"^0x[a-fA-F0-9]{40}$").unwrap();
-        if !address_regex.is_match(&self.address_from) {
-            result.push("Invalid source address format.".to_string());
+        if let Err(err) = source {
+            result.push(err.for_field("address_from", "source address"));
         }
-        if !address_regex.is_match(&self.address_to) {
-            result.push("Invalid destination address format.".to_string());
+        if let Err(err) = destination {
+            result.push(err.for_field("address_to", "destination address"));
         }
 
         if self.amount <= Decimal::ZERO {
-            result.push("Transaction amount must be greater than zero.".to_string());
+            result.push(FieldError::new(
+                ErrorCode::InvalidAmount,
+                "amount",
+                "Transaction amount must be greater than zero.",
+            ));
+        }
+
+        if !matches!(
+            self.status,
+            TransactionStatus::Pending | TransactionStatus::Confirmed
+        ) {
+            result.push(FieldError::new(
+                ErrorCode::InvalidStatus,
+                "status",
+                "Transactions can only be created as pending or confirmed.",
+            ));
+        }
+
+        match asset {
+            Some(asset) => result.extend(asset.validate_amount(self.amount)),
+            None => result.push(FieldError::new(
+                ErrorCode::UnknownAsset,
+                "asset",
+                "Unknown asset.",
+            )),
         }
 
         result
@@ -77,12 +278,33 @@ impl From<CreateTransactionRequest> for Transaction {
             address_from: request.address_from,
             address_to: request.address_to,
             amount: request.amount,
+            asset: request.asset,
             transaction_type: request.transaction_type,
             created_at: None,
+            reversal_of: None,
+            reversed_by: None,
+            status: request.status,
+            api_key_id: None,
+            nonce: request.nonce,
+            settled_by_api_key_id: None,
         }
     }
 }
 
+const TIMESTAMP_FORMAT: &str = "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond]";
+
+/// `created_at` as listed and exported: `YYYY-MM-DD HH:MM:SS.fff`, in UTC.
+pub(crate) fn format_timestamp(date: &PrimitiveDateTime) -> Result<String, time::error::Format> {
+    let format = format_description::parse(TIMESTAMP_FORMAT).unwrap();
+    date.format(&format)
+}
+
+/// Reads a timestamp written by [`format_timestamp`].
+pub(crate) fn parse_timestamp(date: &str) -> Result<PrimitiveDateTime, time::error::Parse> {
+    let format = format_description::parse(TIMESTAMP_FORMAT).unwrap();
+    PrimitiveDateTime::parse(date, &format)
+}
+
 fn serialize_primitive_date<S>(
     date: &Option<PrimitiveDateTime>,
     serializer: S,
@@ -91,10 +313,7 @@ where
     S: Serializer,
 {
     if let Some(date) = date {
-        let format =
-            format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond]")
-                .unwrap();
-        let date_string = date.format(&format).map_err(serde::ser::Error::custom)?;
+        let date_string = format_timestamp(date).map_err(serde::ser::Error::custom)?;
         serializer.serialize_some(&date_string)
     } else {
         serializer.serialize_none()
@@ -1,97 +1,560 @@
-use crate::api::{ErrorResponse, build_json_response};
-use crate::modules::transactions::repository::{get_all_transactions, get_transactions_by_address};
-use crate::modules::transactions::request::CreateTransactionRequest;
-use crate::modules::transactions::response::Transaction;
+use crate::api::build_json_response;
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::configurations::Config;
+use crate::modules::address;
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::response::ApiKey;
+use crate::modules::events::{EventBus, publish_balances, publish_transaction};
+use crate::modules::ledger::repository::insert_postings;
+use crate::modules::metrics::metrics;
+use crate::modules::transactions::export::{ExportFormat, ExportQuery, export_transactions};
+use crate::modules::transactions::import::{ImportQuery, import_transactions};
+use crate::modules::transactions::repository::{
+    IdempotencyKey, InsertOutcome, TransactionRepository, get_transaction_for_update, lock_address,
+    set_reversed_by, set_transaction_status,
+};
+use crate::modules::transactions::request::{
+    BatchMode, BatchQuery, CreateTransactionRequest, ListTransactionsQuery, MAX_BATCH_SIZE,
+    TransactionFilter,
+};
+use crate::modules::transactions::response::{
+    BatchItemResult, BatchItemStatus, BatchResponse, NextNonce, Transaction, TransactionPage,
+};
+use crate::modules::transactions::{TransactionStatus, TransactionType};
+use crate::modules::users::ensure_may_spend;
+use crate::modules::users::repository::get_address_owner;
+use crate::modules::wallet::repository::{get_account_policy, get_available_balance};
 use actix_web::http::StatusCode;
-use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
 use sqlx::PgPool;
 
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
 #[get("")]
-async fn get_transactions(req: HttpRequest) -> impl Responder {
-    let pool = match req.app_data::<web::Data<PgPool>>() {
-        Some(pool) => pool,
-        None => {
-            return HttpResponse::InternalServerError().json("Database pool not found");
-        }
-    };
+async fn get_transactions(
+    repository: web::Data<dyn TransactionRepository>,
+    query: web::Query<ListTransactionsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let filter = query
+        .into_inner()
+        .into_filter()
+        .map_err(ApiError::Validation)?;
 
-    match get_all_transactions(pool).await {
-        Ok(transactions) => build_json_response(transactions, StatusCode::OK),
-        Err(err) => {
-            eprintln!("Failed to fetch transactions: {}", err);
+    let rows = repository.list(&filter).await?;
+    Ok(build_json_response(
+        TransactionPage::from_rows(rows, filter.limit),
+        StatusCode::OK,
+    ))
+}
 
-            if err.downcast_ref::<sqlx::Error>().is_some() {
-                HttpResponse::InternalServerError().json("Database error occurred")
-            } else {
-                HttpResponse::InternalServerError().json("An unexpected error occurred")
-            }
-        }
-    }
+/// Every transaction matching the listing filters, as CSV or JSON Lines.
+#[get("export")]
+async fn export_all_transactions(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListTransactionsQuery>,
+    export: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let filter = query
+        .into_inner()
+        .into_filter()
+        .map_err(ApiError::Validation)?;
+
+    Ok(export_response(
+        pool.get_ref().clone(),
+        None,
+        filter,
+        export.format,
+    ))
 }
 
-#[get("{address}")]
-async fn get_transactions_address(req: HttpRequest, path: web::Path<String>) -> impl Responder {
-    let pool = match req.app_data::<web::Data<PgPool>>() {
-        Some(pool) => pool,
-        None => {
-            return HttpResponse::InternalServerError().json("Database pool not found");
-        }
+#[get("{address}/export")]
+async fn export_address_transactions(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<ListTransactionsQuery>,
+    export: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+    let filter = query
+        .into_inner()
+        .into_filter()
+        .map_err(ApiError::Validation)?;
+
+    Ok(export_response(
+        pool.get_ref().clone(),
+        Some(address),
+        filter,
+        export.format,
+    ))
+}
+
+fn export_response(
+    pool: PgPool,
+    address: Option<String>,
+    filter: TransactionFilter,
+    format: ExportFormat,
+) -> HttpResponse {
+    let filename = format!(
+        "transactions{}.{}",
+        address
+            .as_deref()
+            .map(|address| format!("-{}", address))
+            .unwrap_or_default(),
+        format.extension()
+    );
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        ))
+        .streaming(export_transactions(pool, address, filter, format))
+}
+
+/// Imports historical transactions from a CSV body; see
+/// [`import_transactions`]. Answers 201 when they were written, 200 for a
+/// clean dry run and 422 when any row is invalid.
+pub(crate) async fn import_transactions_csv(
+    pool: web::Data<PgPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    let report = import_transactions(&pool, &body, query.dry_run).await?;
+
+    let status = if report.committed {
+        StatusCode::CREATED
+    } else if report.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
     };
+    Ok(build_json_response(report, status))
+}
 
-    let address = path.into_inner();
+#[get("{address}")]
+async fn get_transactions_address(
+    repository: web::Data<dyn TransactionRepository>,
+    path: web::Path<String>,
+    query: web::Query<ListTransactionsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+    let filter = query
+        .into_inner()
+        .into_filter()
+        .map_err(ApiError::Validation)?;
 
-    match get_transactions_by_address(pool, &address).await {
-        Ok(transactions) => build_json_response(transactions, StatusCode::OK),
-        Err(err) => {
-            eprintln!("Failed to fetch transactions: {}", err);
+    let rows = repository.list_by_address(&address, &filter).await?;
+    Ok(build_json_response(
+        TransactionPage::from_rows(rows, filter.limit),
+        StatusCode::OK,
+    ))
+}
 
-            if err.downcast_ref::<sqlx::Error>().is_some() {
-                HttpResponse::InternalServerError().json("Database error occurred")
-            } else {
-                HttpResponse::InternalServerError().json("An unexpected error occurred")
-            }
-        }
-    }
+#[get("nonce/{address}")]
+async fn get_next_nonce(
+    repository: web::Data<dyn TransactionRepository>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+    let nonce = repository.next_nonce(&address).await?;
+
+    Ok(build_json_response(
+        NextNonce { address, nonce },
+        StatusCode::OK,
+    ))
 }
 
 #[post("")]
 async fn create_transaction(
     req: HttpRequest,
+    config: web::Data<Config>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
     body: web::Json<CreateTransactionRequest>,
-) -> impl Responder {
-    let pool = match req.app_data::<web::Data<PgPool>>() {
-        Some(pool) => pool,
-        None => {
-            return HttpResponse::InternalServerError().json("Database pool not found");
-        }
+) -> Result<HttpResponse, ApiError> {
+    let mut request = body.into_inner();
+    request.normalize_addresses();
+
+    let idempotency_key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => match value.to_str() {
+            Ok(key) if !key.is_empty() && key.len() <= 255 => Some(IdempotencyKey {
+                key: key.to_string(),
+                fingerprint: request.fingerprint(),
+                ttl_seconds: config.idempotency.ttl_seconds,
+            }),
+            _ => {
+                return Err(ApiError::Validation(vec![FieldError::new(
+                    ErrorCode::InvalidIdempotencyKey,
+                    IDEMPOTENCY_KEY_HEADER,
+                    format!(
+                        "{} must be a non-empty string of at most 255 characters.",
+                        IDEMPOTENCY_KEY_HEADER
+                    ),
+                )]));
+            }
+        },
+        None => None,
     };
 
-    let transaction: Transaction = body.into_inner().into();
+    let transaction =
+        authorize_request(&config, repository.get_ref(), api_key.as_deref(), request).await?;
+    let transaction_type = transaction.transaction_type;
 
-    let previous_transactions =
-        match get_transactions_by_address(pool, &transaction.address_from).await {
-            Ok(transactions) => transactions,
-            Err(err) => {
-                eprintln!("Failed to fetch transactions: {}", err);
+    match repository
+        .insert(transaction, idempotency_key.as_ref())
+        .await?
+    {
+        InsertOutcome::Created(id) => {
+            metrics().transaction_created(transaction_type);
+            publish_transaction(&events, repository.get_ref(), id).await;
+            Ok(build_json_response(id, StatusCode::CREATED))
+        }
+        InsertOutcome::Replayed(stored) => {
+            let status = StatusCode::from_u16(stored.status as u16).unwrap_or(StatusCode::OK);
+            Ok(build_json_response(stored.transaction_id, status))
+        }
+        InsertOutcome::KeyReused => Err(ApiError::Unprocessable(FieldError::new(
+            ErrorCode::IdempotencyKeyReused,
+            IDEMPOTENCY_KEY_HEADER,
+            format!(
+                "{} was already used for a different request.",
+                IDEMPOTENCY_KEY_HEADER
+            ),
+        ))),
+        InsertOutcome::Rejected(errors) => Err(ApiError::Validation(errors)),
+    }
+}
 
-                return if err.downcast_ref::<sqlx::Error>().is_some() {
-                    HttpResponse::InternalServerError().json("Database error occurred")
-                } else {
-                    HttpResponse::InternalServerError().json("An unexpected error occurred")
-                };
+/// Creates many transactions at once, each validated against the balances
+/// left by the items before it. In the default `atomic` mode nothing is
+/// written unless every item is valid; in `best_effort` mode every valid item
+/// is. Answers 201 when every item was created, 422 when none was, and 207
+/// otherwise, with the id or errors of each item.
+#[post("batch")]
+async fn create_transaction_batch(
+    config: web::Data<Config>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    query: web::Query<BatchQuery>,
+    body: web::Json<Vec<CreateTransactionRequest>>,
+) -> Result<HttpResponse, ApiError> {
+    let requests = body.into_inner();
+    let mode = query.mode;
+
+    if requests.is_empty() || requests.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::Validation(vec![FieldError::general(
+            ErrorCode::InvalidParameter,
+            format!(
+                "A batch must hold between 1 and {} transactions.",
+                MAX_BATCH_SIZE
+            ),
+        )]));
+    }
+
+    let types: Vec<TransactionType> = requests
+        .iter()
+        .map(|request| request.transaction_type)
+        .collect();
+    let mut items = Vec::with_capacity(requests.len());
+    for mut request in requests {
+        request.normalize_addresses();
+        let item =
+            match authorize_request(&config, repository.get_ref(), api_key.as_deref(), request)
+                .await
+            {
+                Ok(transaction) => Ok(transaction),
+                Err(ApiError::Validation(errors)) => Err(errors),
+                Err(ApiError::Forbidden(error)) => Err(vec![error]),
+                Err(err) => return Err(err),
+            };
+        items.push(item);
+    }
+
+    let outcomes = repository
+        .insert_batch(items, mode == BatchMode::Atomic)
+        .await?;
+    let committed = mode == BatchMode::BestEffort || outcomes.iter().all(Result::is_ok);
+
+    let mut results = Vec::with_capacity(outcomes.len());
+    for (index, outcome) in outcomes.into_iter().enumerate() {
+        results.push(match outcome {
+            Ok(id) if committed => {
+                metrics().transaction_created(types[index]);
+                publish_transaction(&events, repository.get_ref(), id).await;
+                BatchItemResult {
+                    index,
+                    status: BatchItemStatus::Created,
+                    id: Some(id),
+                    errors: vec![],
+                }
+            }
+            Ok(_) => BatchItemResult {
+                index,
+                status: BatchItemStatus::Skipped,
+                id: None,
+                errors: vec![],
+            },
+            Err(errors) => {
+                for error in &errors {
+                    metrics().validation_failed(error.code);
+                }
+                BatchItemResult {
+                    index,
+                    status: BatchItemStatus::Rejected,
+                    id: None,
+                    errors,
+                }
             }
-        };
+        });
+    }
+
+    let created = results
+        .iter()
+        .filter(|result| result.status == BatchItemStatus::Created)
+        .count();
+    let status = if created == results.len() {
+        StatusCode::CREATED
+    } else if created == 0 {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::MULTI_STATUS
+    };
+
+    Ok(build_json_response(BatchResponse { mode, results }, status))
+}
 
-    let errors = transaction.validate(&previous_transactions);
-    if !errors.is_empty() {
-        return build_json_response(ErrorResponse { message: errors }, StatusCode::BAD_REQUEST);
+/// Checks what a transaction request needs beyond validation against the
+/// source address: its signature, if any or required, and otherwise that
+/// the caller may spend from the address. Addresses must already be
+/// normalized.
+async fn authorize_request(
+    config: &Config,
+    repository: &dyn TransactionRepository,
+    api_key: Option<&ApiKey>,
+    request: CreateTransactionRequest,
+) -> Result<Transaction, ApiError> {
+    let signed = request
+        .verify_signature()
+        .map_err(|error| ApiError::Validation(vec![error]))?;
+    // Every transaction debits address_from, whatever its type.
+    if !signed && config.signatures.required {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            ErrorCode::SignatureRequired,
+            "signature",
+            "Transactions must be signed by address_from.",
+        )]));
     }
 
-    match transaction.insert(pool).await {
-        Ok(id) => build_json_response(id, StatusCode::CREATED),
-        Err(e) => {
-            eprintln!("Failed to insert transaction: {}", e);
-            HttpResponse::InternalServerError().json("Failed to create transaction")
-        }
+    let mut transaction = Transaction::from(request);
+
+    // Every transaction debits its source address, so the caller must own
+    // it. A signature by the address proves that on its own.
+    if !signed {
+        let owner = repository.address_owner(&transaction.address_from).await?;
+        ensure_may_spend(api_key, owner, Some("address_from"))?;
+    }
+    transaction.api_key_id = api_key.map(|api_key| api_key.id);
+
+    Ok(transaction)
+}
+
+#[post("{id}/reverse")]
+async fn reverse_transaction(
+    pool: web::Data<PgPool>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    let mut db_tx = pool.begin().await?;
+
+    // Locking the original row makes concurrent reversals of the same
+    // transaction wait for each other, so only one of them can succeed.
+    let original = get_transaction_for_update(&mut *db_tx, id)
+        .await?
+        .ok_or_else(transaction_not_found)?;
+
+    if original.reversed_by.is_some() {
+        return Err(ApiError::conflict(
+            ErrorCode::AlreadyReversed,
+            "Transaction has already been reversed.",
+        ));
+    }
+    if original.status != TransactionStatus::Confirmed {
+        return Err(ApiError::conflict(
+            ErrorCode::NotReversible,
+            "Only confirmed transactions can be reversed.",
+        ));
+    }
+    if original.reversal_of.is_some() {
+        return Err(ApiError::conflict(
+            ErrorCode::NotReversible,
+            "A reversal cannot itself be reversed.",
+        ));
     }
+
+    let mut reversal = original.reversal();
+
+    // The reversal takes funds back from the original recipient, so the
+    // caller must own that address.
+    let owner = get_address_owner(&mut *db_tx, &reversal.address_from).await?;
+    ensure_may_spend(api_key.as_deref(), owner, None)?;
+    reversal.api_key_id = api_key.map(|api_key| api_key.id);
+
+    lock_address(&mut *db_tx, &reversal.address_from).await?;
+
+    let balance =
+        get_available_balance(&mut *db_tx, &reversal.address_from, &reversal.asset).await?;
+    let policy = get_account_policy(&mut *db_tx, &reversal.address_from)
+        .await?
+        .unwrap_or_default();
+
+    if !policy.allows(balance, reversal.amount) {
+        return Err(ApiError::Validation(vec![FieldError::general(
+            ErrorCode::InsufficientBalance,
+            "Insufficient balance",
+        )]));
+    }
+
+    let reversal_type = reversal.transaction_type;
+    let reversal_id = reversal.insert(&mut db_tx).await?;
+    set_reversed_by(&mut *db_tx, id, reversal_id).await?;
+    db_tx.commit().await?;
+    metrics().transaction_created(reversal_type);
+
+    publish_transaction(&events, repository.get_ref(), reversal_id).await;
+
+    Ok(build_json_response(reversal_id, StatusCode::CREATED))
+}
+
+#[post("{id}/confirm")]
+async fn confirm_transaction(
+    pool: web::Data<PgPool>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let api_key = api_key.as_deref();
+    transition_transaction(
+        pool,
+        repository,
+        events,
+        api_key,
+        id,
+        TransactionStatus::Confirmed,
+    )
+    .await
+}
+
+#[post("{id}/cancel")]
+async fn cancel_transaction(
+    pool: web::Data<PgPool>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let api_key = api_key.as_deref();
+    transition_transaction(
+        pool,
+        repository,
+        events,
+        api_key,
+        id,
+        TransactionStatus::Cancelled,
+    )
+    .await
+}
+
+#[post("{id}/fail")]
+async fn fail_transaction(
+    pool: web::Data<PgPool>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let api_key = api_key.as_deref();
+    transition_transaction(
+        pool,
+        repository,
+        events,
+        api_key,
+        id,
+        TransactionStatus::Failed,
+    )
+    .await
+}
+
+/// Moves transaction `id` to `next`, posting it to the ledger when it becomes
+/// confirmed. Only admins settle transactions; the owner of the source
+/// address may cancel them.
+async fn transition_transaction(
+    pool: web::Data<PgPool>,
+    repository: web::Data<dyn TransactionRepository>,
+    events: web::Data<EventBus>,
+    api_key: Option<&ApiKey>,
+    id: i32,
+    next: TransactionStatus,
+) -> Result<HttpResponse, ApiError> {
+    if next != TransactionStatus::Cancelled
+        && let Some(api_key) = api_key
+        && !api_key.allows(Scope::Admin)
+    {
+        return Err(ApiError::Forbidden(FieldError::general(
+            ErrorCode::InsufficientScope,
+            format!("The API key lacks the {} scope.", Scope::Admin),
+        )));
+    }
+
+    let mut db_tx = pool.begin().await?;
+
+    let mut transaction = get_transaction_for_update(&mut *db_tx, id)
+        .await?
+        .ok_or_else(transaction_not_found)?;
+
+    let owner = get_address_owner(&mut *db_tx, &transaction.address_from).await?;
+    ensure_may_spend(api_key, owner, None)?;
+
+    if !transaction.status.can_transition_to(next) {
+        return Err(ApiError::conflict(
+            ErrorCode::InvalidTransition,
+            format!(
+                "Cannot move a transaction from {:?} to {:?}.",
+                transaction.status, next
+            ),
+        ));
+    }
+
+    // Settling changes the available balance of the source address.
+    lock_address(&mut *db_tx, &transaction.address_from).await?;
+
+    let api_key_id = api_key.map(|api_key| api_key.id);
+    set_transaction_status(&mut *db_tx, id, next, api_key_id).await?;
+    if next == TransactionStatus::Confirmed {
+        insert_postings(&mut db_tx, id, &transaction.postings()).await?;
+    }
+
+    transaction.status = next;
+    transaction.settled_by_api_key_id = api_key_id;
+    transaction.insert_event(&mut db_tx, id).await?;
+
+    db_tx.commit().await?;
+
+    publish_balances(&events, repository.get_ref(), &transaction).await;
+
+    Ok(build_json_response(transaction, StatusCode::OK))
+}
+
+fn transaction_not_found() -> ApiError {
+    ApiError::not_found(ErrorCode::TransactionNotFound, "Transaction not found.")
 }
@@ -0,0 +1,1388 @@
+use crate::api::api_routes;
+use crate::configurations::{
+    Api, Config, Db, Holds, Idempotency, Signatures, Webhooks, run_migrations,
+};
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::services::create_api_key;
+use crate::modules::assets::DEFAULT_ASSET;
+use crate::modules::events::EventBus;
+use crate::modules::ledger::repository::find_unbalanced_transactions;
+use crate::modules::signature::{SignatureScheme, SignedTransaction};
+use crate::modules::transactions::TransactionType;
+use crate::modules::transactions::memory::InMemoryTransactionRepository;
+use crate::modules::transactions::repository::{PgTransactionRepository, TransactionRepository};
+use crate::modules::transactions::{api_config, import_config};
+use crate::modules::users::repository::{insert_user, set_address_owner};
+use crate::modules::wallet::policy::{AccountPolicy, OverdraftPolicy};
+use crate::modules::wallet::repository::{
+    get_available_balance, get_stored_balance, rebuild_balances, set_account_policy,
+};
+use crate::modules::wallet::services::{MAX_BALANCE_CHECK_SAMPLE, verify_stored_balances};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{StatusCode, header};
+use actix_web::{App, test, web};
+use futures_util::future::join_all;
+use k256::ecdsa::SigningKey;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Connects to the database named by `DATABASE_URL`, or returns `None` so
+/// database-backed tests are skipped when no database is available.
+pub(crate) async fn test_pool() -> Option<PgPool> {
+    let url = std::env::var("DATABASE_URL").ok()?;
+    let pool = PgPoolOptions::new()
+        .max_connections(10)
+        .connect(&url)
+        .await
+        .expect("Failed to connect to DATABASE_URL");
+    run_migrations(&pool)
+        .await
+        .expect("Failed to migrate the test database");
+    Some(pool)
+}
+
+/// The PostgreSQL repository, as `start_api` registers it.
+pub(crate) fn pg_repository(pool: &PgPool) -> web::Data<dyn TransactionRepository> {
+    let repository: Arc<dyn TransactionRepository> =
+        Arc::new(PgTransactionRepository::new(pool.clone()));
+    web::Data::from(repository)
+}
+
+pub(crate) fn test_config() -> Config {
+    Config {
+        api: Api {
+            bind: "127.0.0.1:0".to_string(),
+            workers: 1,
+            allowed_origins: vec![],
+        },
+        db: Db {
+            url: String::new(),
+            max_connections: 10,
+            migrate_on_startup: false,
+        },
+        idempotency: Idempotency { ttl_seconds: 60 },
+        holds: Holds {
+            sweep_interval_seconds: 1,
+        },
+        signatures: Signatures::default(),
+        webhooks: Webhooks::default(),
+    }
+}
+
+pub(crate) fn unique_address(salt: u64) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("0x{:024x}{:016x}", nanos, salt)
+}
+
+/// The policy of issuer accounts, which fund other addresses.
+pub(crate) fn unlimited() -> AccountPolicy {
+    AccountPolicy {
+        policy: OverdraftPolicy::Unlimited,
+        overdraft_limit: Decimal::ZERO,
+    }
+}
+
+/// A fresh address set up as an issuer account, to deposit from.
+pub(crate) async fn issuer(pool: &PgPool, salt: u64) -> String {
+    let address = unique_address(salt);
+    set_account_policy(pool, &address, &unlimited())
+        .await
+        .unwrap();
+    address
+}
+
+#[actix_web::test]
+async fn test_concurrent_withdrawals_never_overdraw() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 1).await;
+    let address = unique_address(2);
+    let recipient = unique_address(3);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let deposit = test::TestRequest::post()
+        .uri("/api/transactions")
+        .set_json(json!({
+            "address_from": funder,
+            "address_to": address,
+            "amount": "100",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, deposit).await.status(),
+        StatusCode::CREATED
+    );
+
+    let withdrawals = (0..50).map(|_| {
+        let request = test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": address,
+                "address_to": recipient,
+                "amount": "10",
+                "transaction_type": "Withdrawal",
+            }))
+            .to_request();
+        test::call_service(&app, request)
+    });
+    let responses = join_all(withdrawals).await;
+
+    let created = responses
+        .iter()
+        .filter(|response| response.status() == StatusCode::CREATED)
+        .count();
+    assert_eq!(created, 10);
+
+    let balance: Decimal = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(CASE WHEN address_to = $1 THEN amount ELSE -amount END), 0)
+         FROM transactions
+         WHERE address_from = $1 OR address_to = $1",
+    )
+    .bind(&address)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(balance, Decimal::ZERO);
+}
+
+#[actix_web::test]
+async fn test_idempotency_key_replays_and_rejects_mismatch() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let from = issuer(&pool, 4).await;
+    let to = unique_address(5);
+    let key = format!("key-{}", from);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let body = json!({
+        "address_from": from,
+        "address_to": to,
+        "amount": "25",
+        "transaction_type": "Deposit",
+    });
+
+    let first = test::TestRequest::post()
+        .uri("/api/transactions")
+        .insert_header(("Idempotency-Key", key.as_str()))
+        .set_json(&body)
+        .to_request();
+    let first = test::call_service(&app, first).await;
+    assert_eq!(first.status(), StatusCode::CREATED);
+    let first_id: i32 = test::read_body_json(first).await;
+
+    let replay = test::TestRequest::post()
+        .uri("/api/transactions")
+        .insert_header(("Idempotency-Key", key.as_str()))
+        .set_json(json!({
+            "address_from": from,
+            "address_to": to,
+            "amount": "25.00",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    let replay = test::call_service(&app, replay).await;
+    assert_eq!(replay.status(), StatusCode::CREATED);
+    let replay_id: i32 = test::read_body_json(replay).await;
+    assert_eq!(replay_id, first_id);
+
+    let mismatch = test::TestRequest::post()
+        .uri("/api/transactions")
+        .insert_header(("Idempotency-Key", key.as_str()))
+        .set_json(json!({
+            "address_from": from,
+            "address_to": to,
+            "amount": "30",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, mismatch).await.status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE address_from = $1")
+            .bind(&from)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[actix_web::test]
+async fn test_address_listing_pages_through_filtered_results() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(6);
+    let other = unique_address(7);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let mut deposit_ids = vec![];
+    for amount in 1..=5 {
+        let funder = issuer(&pool, 100 + amount).await;
+        let request = test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": funder,
+                "address_to": address,
+                "amount": amount.to_string(),
+                "transaction_type": "Deposit",
+            }))
+            .to_request();
+        deposit_ids.push(test::call_and_read_body_json::<_, _, i32>(&app, request).await);
+    }
+    let request = test::TestRequest::post()
+        .uri("/api/transactions")
+        .set_json(json!({
+            "address_from": address,
+            "address_to": other,
+            "amount": "1",
+            "transaction_type": "Withdrawal",
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::CREATED
+    );
+
+    let mut seen = vec![];
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut uri = format!(
+            "/api/transactions/{}?type=Deposit&limit=2&order=desc",
+            address
+        );
+        if let Some(cursor) = &cursor {
+            uri.push_str(&format!("&cursor={}", cursor));
+        }
+        let page: serde_json::Value =
+            test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request())
+                .await;
+
+        for tx in page["data"].as_array().unwrap() {
+            assert_eq!(tx["transaction_type"], "Deposit");
+            seen.push(tx["id"].as_i64().unwrap() as i32);
+        }
+        match page["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => break,
+        }
+    }
+
+    deposit_ids.reverse();
+    assert_eq!(seen, deposit_ids);
+
+    let request = test::TestRequest::get()
+        .uri(&format!(
+            "/api/transactions/{}?min_amount=2&max_amount=3",
+            address
+        ))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(page["data"].as_array().unwrap().len(), 2);
+
+    let request = test::TestRequest::get()
+        .uri("/api/transactions?cursor=invalid")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[actix_web::test]
+async fn test_exports_stream_every_filtered_transaction() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 41).await;
+    let address = unique_address(42);
+    let other = unique_address(43);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let mut ids = vec![];
+    for (from, to, amount, transaction_type) in [
+        (&funder, &address, "10", "Deposit"),
+        (&funder, &address, "2.5", "Deposit"),
+        (&address, &other, "4", "Withdrawal"),
+    ] {
+        let request = test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": transaction_type,
+            }))
+            .to_request();
+        ids.push(test::call_and_read_body_json::<_, _, i32>(&app, request).await);
+    }
+
+    let request = test::TestRequest::get()
+        .uri(&format!(
+            "/api/transactions/{}/export?type=Deposit",
+            address
+        ))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,created_at,address_from,address_to,amount,asset,transaction_type,status,\
+         reversal_of,reversed_by,api_key_id,nonce"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with(&format!("{},", ids[0])));
+    let fields: Vec<&str> = lines[2].split(',').collect();
+    assert_eq!(
+        fields[2..8],
+        [
+            funder.as_str(),
+            address.as_str(),
+            "2.5",
+            "ETH",
+            "Deposit",
+            "confirmed"
+        ]
+    );
+
+    let request = test::TestRequest::get()
+        .uri(&format!(
+            "/api/transactions/export?format=jsonl&counterparty={}&order=desc",
+            address
+        ))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/jsonl"
+    );
+    let body = test::read_body(response).await;
+    let exported: Vec<i64> = body
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            serde_json::from_slice::<serde_json::Value>(line).unwrap()["id"]
+                .as_i64()
+                .unwrap()
+        })
+        .collect();
+    let expected: Vec<i64> = ids.iter().rev().map(|id| *id as i64).collect();
+    assert_eq!(exported, expected);
+
+    let request = test::TestRequest::get()
+        .uri("/api/transactions/export?format=xml")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[actix_web::test]
+async fn test_imports_validate_chronologically_and_commit_atomically() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 44).await;
+    let address = unique_address(45);
+    let recipient = unique_address(46);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions/import").configure(import_config))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    // The withdrawal comes first in the file but is only funded by the
+    // deposit made a day earlier.
+    let valid = format!(
+        "created_at,address_from,address_to,amount,transaction_type\n\
+         2020-01-02T00:00:00Z,{address},{recipient},4,Withdrawal\n\
+         2020-01-01 00:00:00.0,{funder},{address},10,Deposit\n"
+    );
+    let invalid = format!(
+        "{valid}\
+         2020-01-03T00:00:00Z,{address},{recipient},100,Withdrawal\n\
+         yesterday,{funder},{address},1,Deposit\n"
+    );
+    let import = |csv: &str, dry_run: bool| {
+        test::TestRequest::post()
+            .uri(&format!("/api/transactions/import?dry_run={}", dry_run))
+            .insert_header(("content-type", "text/csv"))
+            .set_payload(csv.to_string())
+            .to_request()
+    };
+    let listed = || async {
+        let request = test::TestRequest::get()
+            .uri(&format!("/api/transactions/{}?order=asc", address))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        page["data"].as_array().unwrap().clone()
+    };
+
+    let response = test::call_service(&app, import(&valid, true)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(report["committed"], false);
+    assert_eq!(report["rows"], 2);
+    assert!(listed().await.is_empty());
+
+    let response = test::call_service(&app, import(&invalid, false)).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let report: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(report["committed"], false);
+    assert_eq!(report["errors"][0]["line"], 4);
+    assert_eq!(
+        report["errors"][0]["errors"][0]["code"],
+        "INSUFFICIENT_BALANCE"
+    );
+    assert_eq!(report["errors"][1]["line"], 5);
+    assert_eq!(report["errors"][1]["errors"][0]["field"], "created_at");
+    assert!(listed().await.is_empty());
+
+    let response = test::call_service(&app, import(&valid, false)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let transactions = listed().await;
+    let dates: Vec<&str> = transactions
+        .iter()
+        .map(|tx| tx["created_at"].as_str().unwrap())
+        .collect();
+    assert_eq!(dates, ["2020-01-01 00:00:00.0", "2020-01-02 00:00:00.0"]);
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::from(6)
+    );
+
+    let response = test::call_service(&app, import("address_from\n", false)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_created_transactions_keep_the_ledger_balanced() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(8);
+    let other = issuer(&pool, 9).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    for (from, to, amount, transaction_type) in [
+        (&other, &address, "40", "Deposit"),
+        (&address, &other, "15.5", "Withdrawal"),
+    ] {
+        let request = test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": transaction_type,
+            }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::CREATED
+        );
+    }
+
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(245, 1))
+    );
+    assert!(
+        find_unbalanced_transactions(&pool)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[actix_web::test]
+async fn test_rebuilt_balances_match_history() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(10);
+    let other = issuer(&pool, 11).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/transactions")
+        .set_json(json!({
+            "address_from": other,
+            "address_to": address,
+            "amount": "12",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::CREATED
+    );
+
+    sqlx::query("UPDATE balances SET balance = 0 WHERE address = $1")
+        .bind(&address)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    rebuild_balances(&pool).await.unwrap();
+
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(12, 0))
+    );
+    let check = verify_stored_balances(&pool, MAX_BALANCE_CHECK_SAMPLE)
+        .await
+        .unwrap();
+    assert!(check.mismatches.is_empty());
+}
+
+#[actix_web::test]
+async fn test_reversal_links_both_transactions_once() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 12).await;
+    let address = unique_address(13);
+    let recipient = unique_address(14);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let create = |from: &str, to: &str, amount: &str, transaction_type: &str| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": transaction_type,
+            }))
+            .to_request()
+    };
+    let reverse = |id: i32| {
+        test::TestRequest::post()
+            .uri(&format!("/api/transactions/{}/reverse", id))
+            .to_request()
+    };
+
+    let deposit_id: i32 =
+        test::call_and_read_body_json(&app, create(&funder, &address, "50", "Deposit")).await;
+    let withdrawal_id: i32 =
+        test::call_and_read_body_json(&app, create(&address, &recipient, "50", "Withdrawal")).await;
+
+    // The deposit's recipient has already spent the funds.
+    assert_eq!(
+        test::call_service(&app, reverse(deposit_id)).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let response = test::call_service(&app, reverse(withdrawal_id)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let reversal_id: i32 = test::read_body_json(response).await;
+
+    assert_eq!(
+        test::call_service(&app, reverse(withdrawal_id))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        test::call_service(&app, reverse(reversal_id))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        test::call_service(&app, reverse(i32::MAX)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/transactions/{}", address))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    let find = |id: i32| {
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|tx| tx["id"] == id)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(find(withdrawal_id)["reversed_by"], reversal_id);
+    assert_eq!(find(reversal_id)["reversal_of"], withdrawal_id);
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(50, 0))
+    );
+}
+
+#[actix_web::test]
+async fn test_pending_withdrawals_reserve_funds_until_settled() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 15).await;
+    let address = unique_address(16);
+    let recipient = unique_address(17);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let create = |from: &str, to: &str, amount: &str, transaction_type: &str, status: &str| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": transaction_type,
+                "status": status,
+            }))
+            .to_request()
+    };
+    let transition = |id: i32, action: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/transactions/{}/{}", id, action))
+            .to_request()
+    };
+
+    let _: i32 = test::call_and_read_body_json(
+        &app,
+        create(&funder, &address, "100", "Deposit", "confirmed"),
+    )
+    .await;
+    let pending_id: i32 = test::call_and_read_body_json(
+        &app,
+        create(&address, &recipient, "70", "Withdrawal", "pending"),
+    )
+    .await;
+
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(100, 0))
+    );
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::new(30, 0)
+    );
+
+    // Only 30 is left once the pending withdrawal is reserved.
+    let response = test::call_service(
+        &app,
+        create(&address, &recipient, "40", "Withdrawal", "pending"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = test::call_service(&app, transition(pending_id, "confirm")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let confirmed: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(confirmed["status"], "confirmed");
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(30, 0))
+    );
+
+    assert_eq!(
+        test::call_service(&app, transition(pending_id, "confirm"))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        test::call_service(&app, transition(pending_id, "cancel"))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+
+    let cancelled_id: i32 = test::call_and_read_body_json(
+        &app,
+        create(&address, &recipient, "30", "Withdrawal", "pending"),
+    )
+    .await;
+    assert_eq!(
+        test::call_service(&app, transition(cancelled_id, "cancel"))
+            .await
+            .status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get_available_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Decimal::new(30, 0)
+    );
+    assert_eq!(
+        test::call_service(&app, transition(i32::MAX, "fail"))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let unbalanced = find_unbalanced_transactions(&pool).await.unwrap();
+    assert!(!unbalanced.contains(&pending_id));
+    assert!(!unbalanced.contains(&cancelled_id));
+}
+
+#[actix_web::test]
+async fn test_addresses_are_matched_case_insensitively() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 18).await;
+    let address = unique_address(19);
+    let shouted = format!("0x{}", address[2..].to_uppercase());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let create = |from: &str, to: &str, amount: &str, transaction_type: &str| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": transaction_type,
+            }))
+            .to_request()
+    };
+
+    let response = test::call_service(&app, create(&funder, &shouted, "10", "Deposit")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Both spellings share one balance, so the withdrawal is funded.
+    let response = test::call_service(&app, create(&address, &funder, "4", "Withdrawal")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        get_stored_balance(&pool, &address, DEFAULT_ASSET)
+            .await
+            .unwrap(),
+        Some(Decimal::new(6, 0))
+    );
+
+    // The same address spelled twice is still the same address.
+    let response = test::call_service(&app, create(&shouted, &address, "1", "Withdrawal")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // EIP-55 test vector with the case of its last letter flipped.
+    let response = test::call_service(
+        &app,
+        create(
+            &address,
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD",
+            "1",
+            "Withdrawal",
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/problem+json"
+    );
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["code"], "INVALID_CHECKSUM");
+    assert_eq!(problem["field"], "address_to");
+    assert_eq!(problem["detail"], "Invalid destination address checksum.");
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/transactions/{}", shouted))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    let data = page["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert!(
+        data.iter().all(
+            |tx| tx["address_from"] == address.as_str() || tx["address_to"] == address.as_str()
+        )
+    );
+}
+
+/// Exercises batches against `repository`, with addresses not used before.
+async fn check_batches(
+    repository: web::Data<dyn TransactionRepository>,
+    funder: &str,
+    address: &str,
+    recipient: &str,
+) {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(repository)
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    let item = |from: &str, to: &str, amount: &str, transaction_type: &str| {
+        json!({
+            "address_from": from,
+            "address_to": to,
+            "amount": amount,
+            "transaction_type": transaction_type,
+        })
+    };
+    let batch = |mode: &str, items: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("/api/transactions/batch?mode={}", mode))
+            .set_json(items)
+            .to_request()
+    };
+    let listed = || async {
+        let request = test::TestRequest::get()
+            .uri(&format!("/api/transactions/{}", address))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        page["data"].as_array().unwrap().len()
+    };
+
+    // The second withdrawal overdraws once the first has been counted.
+    let overdrawn = json!([
+        item(funder, address, "10", "Deposit"),
+        item(address, recipient, "6", "Withdrawal"),
+        item(address, recipient, "6", "Withdrawal"),
+    ]);
+
+    let response = test::call_service(&app, batch("atomic", overdrawn.clone())).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    let statuses: Vec<&str> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["skipped", "skipped", "rejected"]);
+    assert_eq!(
+        body["results"][2]["errors"][0]["code"],
+        "INSUFFICIENT_BALANCE"
+    );
+    assert_eq!(listed().await, 0);
+
+    let response = test::call_service(&app, batch("best_effort", overdrawn)).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["mode"], "best_effort");
+    assert!(body["results"][0]["id"].is_i64());
+    assert!(body["results"][1]["id"].is_i64());
+    assert_eq!(body["results"][2]["status"], "rejected");
+    assert_eq!(listed().await, 2);
+
+    let response = test::call_service(
+        &app,
+        batch(
+            "atomic",
+            json!([item(address, recipient, "4", "Withdrawal")]),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(listed().await, 3);
+
+    let response = test::call_service(&app, batch("atomic", json!([]))).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_batches_count_earlier_items_and_honour_their_mode() {
+    let memory = InMemoryTransactionRepository::new();
+    memory.set_account_policy("0x1111111111111111111111111111111111111111", unlimited());
+    let repository: Arc<dyn TransactionRepository> = Arc::new(memory);
+    check_batches(
+        web::Data::from(repository),
+        "0x1111111111111111111111111111111111111111",
+        "0x2222222222222222222222222222222222222222",
+        "0x3333333333333333333333333333333333333333",
+    )
+    .await;
+
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+    check_batches(
+        pg_repository(&pool),
+        &issuer(&pool, 47).await,
+        &unique_address(48),
+        &unique_address(49),
+    )
+    .await;
+}
+
+#[actix_web::test]
+async fn test_api_runs_against_the_in_memory_repository() {
+    let memory = InMemoryTransactionRepository::new();
+    memory.set_account_policy("0x1111111111111111111111111111111111111111", unlimited());
+    let repository: Arc<dyn TransactionRepository> = Arc::new(memory);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::from(repository))
+            .service(web::scope("/api/transactions").configure(api_config))
+            .service(web::scope("/api/wallet").configure(crate::modules::wallet::api_config)),
+    )
+    .await;
+
+    let funder = "0x1111111111111111111111111111111111111111";
+    let address = "0x2222222222222222222222222222222222222222";
+    let recipient = "0x3333333333333333333333333333333333333333";
+
+    let create = |from: &str, to: &str, amount: &str, transaction_type: &str| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "transaction_type": transaction_type,
+            }))
+    };
+
+    let deposit_id: i32 =
+        test::call_and_read_body_json(&app, create(funder, address, "100", "Deposit").to_request())
+            .await;
+
+    let withdrawal = || {
+        create(address, recipient, "60", "Withdrawal")
+            .insert_header(("Idempotency-Key", "in-memory"))
+            .to_request()
+    };
+    let response = test::call_service(&app, withdrawal()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let withdrawal_id: i32 = test::read_body_json(response).await;
+
+    // A retry is replayed instead of withdrawing twice.
+    let response = test::call_service(&app, withdrawal()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let replayed_id: i32 = test::read_body_json(response).await;
+    assert_eq!(replayed_id, withdrawal_id);
+
+    let response = test::call_service(
+        &app,
+        create(address, recipient, "41", "Withdrawal").to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/transactions/{}?limit=1", address))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(page["data"][0]["id"], deposit_id);
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+
+    let request = test::TestRequest::get()
+        .uri(&format!(
+            "/api/transactions/{}?limit=1&cursor={}",
+            address, cursor
+        ))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(page["data"][0]["id"], withdrawal_id);
+    assert!(page["next_cursor"].is_null());
+
+    let request = test::TestRequest::get()
+        .uri("/api/transactions?type=Withdrawal")
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}?asset=ETH", address))
+        .to_request();
+    let balance: Decimal = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(balance, Decimal::new(40, 0));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}", recipient))
+        .to_request();
+    let balances: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(balances, json!([{ "asset": "ETH", "balance": "60" }]));
+}
+
+#[actix_web::test]
+async fn test_signed_withdrawals_use_each_nonce_once() {
+    let mut config = test_config();
+    config.signatures.required = true;
+
+    // The signer is an issuer account, so it needs no funding.
+    let memory = InMemoryTransactionRepository::new();
+    memory.set_account_policy("0x2c7536e3605d9c16a7a3d7b1898e529396a65c23", unlimited());
+    let repository: Arc<dyn TransactionRepository> = Arc::new(memory);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::from(repository))
+            .service(web::scope("/api/transactions").configure(api_config)),
+    )
+    .await;
+
+    // The key behind the web3.js `accounts.sign` examples.
+    let key = SigningKey::from_slice(
+        &hex::decode("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap(),
+    )
+    .unwrap();
+    let signer = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+    let recipient = "0x3333333333333333333333333333333333333333";
+
+    let withdrawal = |amount: &str, nonce: i64, scheme: SignatureScheme| {
+        let digest = SignedTransaction {
+            address_from: signer,
+            address_to: recipient,
+            amount: amount.parse().unwrap(),
+            asset: DEFAULT_ASSET,
+            transaction_type: TransactionType::Withdrawal,
+            nonce,
+        }
+        .digest(scheme);
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+        json!({
+            "address_from": signer,
+            "address_to": recipient,
+            "amount": amount,
+            "transaction_type": "Withdrawal",
+            "signature": format!("0x{}{:02x}", hex::encode(signature.to_bytes()), 27 + recovery_id.to_byte()),
+            "signature_scheme": if scheme == SignatureScheme::Eip712 { "eip712" } else { "eip191" },
+            "nonce": nonce,
+        })
+    };
+    let create = |body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(body)
+            .to_request()
+    };
+    let problem = |response: ServiceResponse| async move {
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let problem: serde_json::Value = test::read_body_json(response).await;
+        problem["code"].as_str().unwrap().to_string()
+    };
+
+    let mut unsigned = withdrawal("10", 0, SignatureScheme::Eip191);
+    unsigned.as_object_mut().unwrap().remove("signature");
+    unsigned.as_object_mut().unwrap().remove("nonce");
+    let response = test::call_service(&app, create(unsigned.clone())).await;
+    assert_eq!(problem(response).await, "SIGNATURE_REQUIRED");
+
+    // A deposit debits its source too, so it needs a signature as well.
+    unsigned["transaction_type"] = json!("Deposit");
+    let response = test::call_service(&app, create(unsigned)).await;
+    assert_eq!(problem(response).await, "SIGNATURE_REQUIRED");
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/transactions/nonce/{}", signer))
+        .to_request();
+    let next: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(next["nonce"], 0);
+
+    let signed = withdrawal("10", 0, SignatureScheme::Eip191);
+    let response = test::call_service(&app, create(signed.clone())).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Replaying the same signed request is rejected.
+    let response = test::call_service(&app, create(signed)).await;
+    assert_eq!(problem(response).await, "INVALID_NONCE");
+
+    let mut tampered = withdrawal("10", 1, SignatureScheme::Eip712);
+    tampered["amount"] = json!("90");
+    let response = test::call_service(&app, create(tampered)).await;
+    assert_eq!(problem(response).await, "INVALID_SIGNATURE");
+
+    let response =
+        test::call_service(&app, create(withdrawal("10", 1, SignatureScheme::Eip712))).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/transactions/nonce/{}", signer))
+        .to_request();
+    let next: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(next["nonce"], 2);
+}
+
+#[actix_web::test]
+async fn test_required_signatures_keep_hold_captures_with_the_owner() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+    let mut config = test_config();
+    config.signatures.required = true;
+
+    // The held address is an issuer account, so it needs no funding.
+    let held = issuer(&pool, 57).await;
+    let own = unique_address(58);
+    let third_party = unique_address(59);
+    let alice = insert_user(&pool, "alice").await.unwrap();
+    assert!(set_address_owner(&pool, alice.id, &held).await.unwrap());
+    assert!(set_address_owner(&pool, alice.id, &own).await.unwrap());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/holds").configure(crate::modules::holds::api_config)),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/holds")
+        .set_json(json!({
+            "address": held,
+            "amount": "10",
+            "expires_at": "2999-01-01T00:00:00Z",
+        }))
+        .to_request();
+    let hold: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    let capture = |address_to: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/holds/{}/capture", hold["id"]))
+            .set_json(json!({ "address_to": address_to }))
+            .to_request()
+    };
+
+    // Sending to someone else would need a signature by the held address.
+    let response = test::call_service(&app, capture(&third_party)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "SIGNATURE_REQUIRED");
+
+    let response = test::call_service(&app, capture(&own)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "captured");
+}
+
+#[actix_web::test]
+async fn test_only_admins_settle_and_only_owners_cancel() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 54).await;
+    let owned = unique_address(55);
+    let recipient = unique_address(56);
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+    let scopes = [Scope::ReadTransactions, Scope::WriteTransactions];
+    let alice = insert_user(&pool, "alice").await.unwrap();
+    assert!(set_address_owner(&pool, alice.id, &owned).await.unwrap());
+    let alice = create_api_key(&pool, "alice", &scopes, Some(alice.id))
+        .await
+        .unwrap();
+    let mallory = insert_user(&pool, "mallory").await.unwrap();
+    let mallory = create_api_key(&pool, "mallory", &scopes, Some(mallory.id))
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let bearer = |key: &str| (header::AUTHORIZATION, format!("Bearer {}", key));
+    let create = |key: &str, from: &str, to: &str, transaction_type: &str, status: &str| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .insert_header(bearer(key))
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": "10",
+                "transaction_type": transaction_type,
+                "status": status,
+            }))
+            .to_request()
+    };
+    let transition = |key: &str, id: i32, action: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/transactions/{}/{}", id, action))
+            .insert_header(bearer(key))
+            .to_request()
+    };
+    let problem = |response: ServiceResponse| async move {
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let problem: serde_json::Value = test::read_body_json(response).await;
+        problem["code"].as_str().unwrap().to_string()
+    };
+
+    let request = create(&admin.key, &funder, &owned, "Deposit", "confirmed");
+    let _: i32 = test::call_and_read_body_json(&app, request).await;
+    let request = create(&admin.key, &funder, &owned, "Deposit", "confirmed");
+    let _: i32 = test::call_and_read_body_json(&app, request).await;
+    let request = create(&alice.key, &owned, &recipient, "Withdrawal", "pending");
+    let cancelled: i32 = test::call_and_read_body_json(&app, request).await;
+    let request = create(&alice.key, &owned, &recipient, "Withdrawal", "pending");
+    let confirmed: i32 = test::call_and_read_body_json(&app, request).await;
+
+    // Only the owner of the source address may cancel.
+    let response = test::call_service(&app, transition(&mallory.key, cancelled, "cancel")).await;
+    assert_eq!(problem(response).await, "NOT_ADDRESS_OWNER");
+
+    // Settling is reserved to admins, even for the owner.
+    for action in ["confirm", "fail"] {
+        let response = test::call_service(&app, transition(&alice.key, confirmed, action)).await;
+        assert_eq!(problem(response).await, "INSUFFICIENT_SCOPE");
+    }
+
+    let request = transition(&alice.key, cancelled, "cancel");
+    let body: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["status"], "cancelled");
+    assert_eq!(body["settled_by_api_key_id"], alice.api_key.id);
+
+    let request = transition(&admin.key, confirmed, "confirm");
+    let body: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["status"], "confirmed");
+    assert_eq!(body["settled_by_api_key_id"], admin.api_key.id);
+}
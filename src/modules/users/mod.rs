@@ -0,0 +1,19 @@
+pub mod repository;
+mod request;
+pub mod response;
+mod services;
+
+#[cfg(test)]
+pub mod test;
+
+use crate::modules::users::services::{bind_address, create_user, get_user, unbind_address};
+use actix_web::web;
+
+pub use crate::modules::users::services::ensure_may_spend;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_user)
+        .service(get_user)
+        .service(bind_address)
+        .service(unbind_address);
+}
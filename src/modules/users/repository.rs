@@ -0,0 +1,88 @@
+use crate::modules::users::response::User;
+use sqlx::{PgExecutor, PgPool};
+
+pub(crate) async fn insert_user(pool: &PgPool, name: &str) -> Result<User, sqlx::Error> {
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (name) VALUES ($1) RETURNING id, name, created_at",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(user)
+}
+
+pub(crate) async fn get_user(pool: &PgPool, id: i32) -> Result<Option<User>, sqlx::Error> {
+    let user = sqlx::query_as::<_, User>("SELECT id, name, created_at FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(user)
+}
+
+/// Addresses owned by user `id`, in order.
+pub(crate) async fn get_user_addresses(pool: &PgPool, id: i32) -> Result<Vec<String>, sqlx::Error> {
+    let addresses =
+        sqlx::query_scalar("SELECT address FROM accounts WHERE user_id = $1 ORDER BY address")
+            .bind(id)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(addresses)
+}
+
+/// The user owning `address`, or `None` when nobody does.
+pub async fn get_address_owner<'e, E>(
+    executor: E,
+    address: &str,
+) -> Result<Option<i32>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let owner =
+        sqlx::query_scalar::<_, Option<i32>>("SELECT user_id FROM accounts WHERE address = $1")
+            .bind(address)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(owner.flatten())
+}
+
+/// Makes user `id` the owner of `address`, opening the account if needed.
+/// Returns `false`, changing nothing, when another user already owns it.
+pub(crate) async fn set_address_owner(
+    pool: &PgPool,
+    id: i32,
+    address: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO accounts (address, user_id) VALUES ($1, $2)
+         ON CONFLICT (address) DO UPDATE
+         SET user_id = EXCLUDED.user_id
+         WHERE accounts.user_id IS NULL OR accounts.user_id = EXCLUDED.user_id",
+    )
+    .bind(address)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Releases `address` from user `id`. Returns `false` when the user did not
+/// own it.
+pub(crate) async fn clear_address_owner(
+    pool: &PgPool,
+    id: i32,
+    address: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("UPDATE accounts SET user_id = NULL WHERE address = $1 AND user_id = $2")
+            .bind(address)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() == 1)
+}
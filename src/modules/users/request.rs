@@ -0,0 +1,34 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::address;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateUserRequest {
+    pub name: String,
+}
+
+impl CreateUserRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        if self.name.trim().is_empty() || self.name.len() > 255 {
+            return vec![FieldError::new(
+                ErrorCode::InvalidParameter,
+                "name",
+                "Name must be between 1 and 255 characters.",
+            )];
+        }
+
+        vec![]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BindAddressRequest {
+    pub address: String,
+}
+
+impl BindAddressRequest {
+    /// The canonical form of the address to bind.
+    pub fn address(&self) -> Result<String, FieldError> {
+        address::normalize(&self.address).map_err(|err| err.for_field("address", "address"))
+    }
+}
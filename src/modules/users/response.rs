@@ -0,0 +1,20 @@
+use crate::modules::holds::response::serialize_utc;
+use serde::Serialize;
+use sqlx::FromRow;
+use time::PrimitiveDateTime;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct User {
+    pub id: i32,
+    pub name: String,
+    #[serde(serialize_with = "serialize_utc")]
+    pub created_at: PrimitiveDateTime,
+}
+
+/// A user together with the addresses it owns.
+#[derive(Debug, Serialize)]
+pub struct UserWithAddresses {
+    #[serde(flatten)]
+    pub user: User,
+    pub addresses: Vec<String>,
+}
@@ -0,0 +1,116 @@
+use crate::api::build_json_response;
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::api_keys::response::ApiKey;
+use crate::modules::users::repository::{
+    clear_address_owner, get_user as find_user, get_user_addresses, insert_user, set_address_owner,
+};
+use crate::modules::users::request::{BindAddressRequest, CreateUserRequest};
+use crate::modules::users::response::UserWithAddresses;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, delete, get, post, web};
+use sqlx::PgPool;
+
+#[post("")]
+async fn create_user(
+    pool: web::Data<PgPool>,
+    body: web::Json<CreateUserRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let request = body.into_inner();
+
+    let errors = request.validate();
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let user = insert_user(&pool, request.name.trim()).await?;
+    Ok(build_json_response(
+        UserWithAddresses {
+            user,
+            addresses: vec![],
+        },
+        StatusCode::CREATED,
+    ))
+}
+
+#[get("{id}")]
+async fn get_user(pool: web::Data<PgPool>, path: web::Path<i32>) -> Result<HttpResponse, ApiError> {
+    let user = user_with_addresses(&pool, path.into_inner()).await?;
+    Ok(build_json_response(user, StatusCode::OK))
+}
+
+/// Gives the user ownership of an address. An address has at most one owner;
+/// binding one owned by somebody else is a conflict.
+#[post("{id}/addresses")]
+async fn bind_address(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    body: web::Json<BindAddressRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let address = body
+        .address()
+        .map_err(|error| ApiError::Validation(vec![error]))?;
+
+    find_user(&pool, id).await?.ok_or_else(user_not_found)?;
+
+    if !set_address_owner(&pool, id, &address).await? {
+        return Err(ApiError::conflict(
+            ErrorCode::AddressOwned,
+            "The address is owned by another user.",
+        ));
+    }
+
+    let user = user_with_addresses(&pool, id).await?;
+    Ok(build_json_response(user, StatusCode::OK))
+}
+
+#[delete("{id}/addresses/{address}")]
+async fn unbind_address(
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, address) = path.into_inner();
+
+    find_user(&pool, id).await?.ok_or_else(user_not_found)?;
+
+    if !clear_address_owner(&pool, id, &address::canonical(&address)).await? {
+        return Err(ApiError::not_found(
+            ErrorCode::AddressNotFound,
+            "The user does not own this address.",
+        ));
+    }
+
+    let user = user_with_addresses(&pool, id).await?;
+    Ok(build_json_response(user, StatusCode::OK))
+}
+
+/// Rejects moving funds out of an address owned by `owner` with `api_key`,
+/// unless the key's user is that owner. `field` names the request field that
+/// holds the address, if any. Requests without a key come from inside the
+/// service and are trusted.
+pub fn ensure_may_spend(
+    api_key: Option<&ApiKey>,
+    owner: Option<i32>,
+    field: Option<&'static str>,
+) -> Result<(), ApiError> {
+    match api_key {
+        Some(api_key) if !api_key.may_spend_from(owner) => Err(ApiError::Forbidden(FieldError {
+            code: ErrorCode::NotAddressOwner,
+            field,
+            detail: "The API key's user does not own this address.".to_string(),
+        })),
+        _ => Ok(()),
+    }
+}
+
+async fn user_with_addresses(pool: &PgPool, id: i32) -> Result<UserWithAddresses, ApiError> {
+    let user = find_user(pool, id).await?.ok_or_else(user_not_found)?;
+    let addresses = get_user_addresses(pool, id).await?;
+
+    Ok(UserWithAddresses { user, addresses })
+}
+
+fn user_not_found() -> ApiError {
+    ApiError::not_found(ErrorCode::UserNotFound, "User not found.")
+}
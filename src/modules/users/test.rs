@@ -0,0 +1,233 @@
+use crate::api::api_routes;
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::services::create_api_key;
+use crate::modules::events::EventBus;
+use crate::modules::transactions::test::{
+    issuer, pg_repository, test_config, test_pool, unique_address,
+};
+use crate::modules::users::repository::{insert_user, set_address_owner};
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{App, test, web};
+use serde_json::json;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+fn bearer(secret: &str) -> (header::HeaderName, String) {
+    (header::AUTHORIZATION, format!("Bearer {}", secret))
+}
+
+#[actix_web::test]
+async fn test_users_only_withdraw_from_addresses_they_own() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 35).await;
+    let owned = unique_address(36);
+    let recipient = unique_address(37);
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/users")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "name": "alice" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let user: serde_json::Value = test::read_body_json(response).await;
+    let user_id = user["id"].as_i64().unwrap();
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/users/{}/addresses", user_id))
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "address": owned }))
+        .to_request();
+    let user: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(user["addresses"], json!([owned]));
+
+    // An address has a single owner.
+    let request = test::TestRequest::post()
+        .uri("/api/users")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "name": "mallory" }))
+        .to_request();
+    let other: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/users/{}/addresses", other["id"]))
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "address": owned }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "ADDRESS_OWNED");
+
+    let request = test::TestRequest::post()
+        .uri("/api/keys")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({
+            "name": "alice",
+            "scopes": ["write:transactions", "read:balance"],
+            "user_id": user_id,
+        }))
+        .to_request();
+    let issued: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(issued["user_id"], user_id);
+    let key = issued["key"].as_str().unwrap().to_string();
+
+    let transfer = |key: &str, from: &str, to: &str, transaction_type: &str| {
+        test::TestRequest::post()
+            .uri("/api/transactions")
+            .insert_header(bearer(key))
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": "10",
+                "transaction_type": transaction_type,
+            }))
+            .to_request()
+    };
+
+    // Admin keys may spend from any address, issuers included.
+    let request = transfer(&admin.key, &funder, &owned, "Deposit");
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let request = transfer(&key, &owned, &recipient, "Withdrawal");
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Whatever its type, a transaction debits its source, so the user may
+    // not name someone else's address as one.
+    for transaction_type in ["Withdrawal", "Deposit"] {
+        let request = transfer(&key, &recipient, &owned, transaction_type);
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let problem: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(problem["code"], "NOT_ADDRESS_OWNER");
+        assert_eq!(problem["field"], "address_from");
+    }
+
+    let request = test::TestRequest::get()
+        .uri("/api/wallet")
+        .insert_header(bearer(&key))
+        .to_request();
+    let wallet: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(
+        wallet,
+        json!([{ "address": owned, "balances": [{ "asset": "ETH", "balance": "0" }] }])
+    );
+
+    // Once released, the address is no longer the user's to spend from.
+    let request = test::TestRequest::delete()
+        .uri(&format!("/api/users/{}/addresses/{}", user_id, owned))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let user: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(user["addresses"], json!([]));
+
+    let request = transfer(&key, &owned, &recipient, "Withdrawal");
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_users_only_see_and_settle_their_own_holds() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 52).await;
+    let owned = unique_address(53);
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+    let scopes = [Scope::ReadTransactions, Scope::WriteTransactions];
+    let alice = insert_user(&pool, "alice").await.unwrap();
+    assert!(set_address_owner(&pool, alice.id, &owned).await.unwrap());
+    let alice = create_api_key(&pool, "alice", &scopes, Some(alice.id))
+        .await
+        .unwrap();
+    let mallory = insert_user(&pool, "mallory").await.unwrap();
+    let mallory = create_api_key(&pool, "mallory", &scopes, Some(mallory.id))
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/transactions")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({
+            "address_from": funder,
+            "address_to": owned,
+            "amount": "10",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let expires_at = (OffsetDateTime::now_utc() + time::Duration::HOUR)
+        .format(&Rfc3339)
+        .unwrap();
+    let request = test::TestRequest::post()
+        .uri("/api/holds")
+        .insert_header(bearer(&alice.key))
+        .set_json(json!({ "address": owned, "amount": "4", "expires_at": expires_at }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let hold: serde_json::Value = test::read_body_json(response).await;
+
+    let get = |key: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/holds/{}", hold["id"]))
+            .insert_header(bearer(key))
+            .to_request()
+    };
+    let release = |key: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/holds/{}/release", hold["id"]))
+            .insert_header(bearer(key))
+            .to_request()
+    };
+
+    for request in [get(&mallory.key), release(&mallory.key)] {
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let problem: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(problem["code"], "NOT_ADDRESS_OWNER");
+    }
+
+    let response = test::call_service(&app, get(&alice.key)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&app, release(&alice.key)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "released");
+}
@@ -1,11 +1,30 @@
+pub mod policy;
+pub mod repository;
+mod request;
+pub mod response;
 pub mod services;
 
 #[cfg(test)]
 pub mod test;
 
-use crate::modules::wallet::services::get_balance;
+use crate::modules::wallet::services::{
+    check_balances, get_available_balance_report, get_balance, get_balance_history,
+    get_negative_balances_report, get_policy, get_wallet, update_policy,
+};
 use actix_web::web;
 
 pub fn api_config(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_balance);
+    cfg.service(get_wallet)
+        .service(get_balance)
+        .service(get_available_balance_report)
+        .service(get_balance_history)
+        .service(get_policy)
+        .service(update_policy);
+}
+
+/// The balance reports for operators, which scan every account and are
+/// mounted on the admin-only `/ledger` scope.
+pub fn ledger_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(check_balances)
+        .service(get_negative_balances_report);
 }
@@ -0,0 +1,99 @@
+use crate::api::error::{ErrorCode, FieldError};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// How far below zero transactions from an account may take it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OverdraftPolicy {
+    /// The balance may never go negative.
+    #[default]
+    Strict,
+    /// The balance may go down to `-overdraft_limit`.
+    Overdraft,
+    /// No limit, for issuer accounts that mint funds into the system.
+    Unlimited,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct AccountPolicy {
+    #[sqlx(rename = "overdraft_policy")]
+    pub policy: OverdraftPolicy,
+    #[serde(default)]
+    pub overdraft_limit: Decimal,
+}
+
+impl AccountPolicy {
+    /// Whether an account holding `balance` may send `amount`.
+    pub fn allows(&self, balance: Decimal, amount: Decimal) -> bool {
+        match self.policy {
+            OverdraftPolicy::Strict => balance - amount >= Decimal::ZERO,
+            OverdraftPolicy::Overdraft => balance - amount >= -self.overdraft_limit,
+            OverdraftPolicy::Unlimited => true,
+        }
+    }
+
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut result = vec![];
+
+        if self.overdraft_limit < Decimal::ZERO {
+            result.push(FieldError::new(
+                ErrorCode::InvalidPolicy,
+                "overdraft_limit",
+                "Overdraft limit cannot be negative.",
+            ));
+        }
+        if self.policy != OverdraftPolicy::Overdraft && self.overdraft_limit != Decimal::ZERO {
+            result.push(FieldError::new(
+                ErrorCode::InvalidPolicy,
+                "overdraft_limit",
+                "Overdraft limit only applies to the overdraft policy.",
+            ));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(policy: OverdraftPolicy, limit: i64) -> AccountPolicy {
+        AccountPolicy {
+            policy,
+            overdraft_limit: Decimal::new(limit, 0),
+        }
+    }
+
+    #[test]
+    fn test_strict_policy_keeps_balance_non_negative() {
+        let strict = policy(OverdraftPolicy::Strict, 0);
+        assert!(strict.allows(Decimal::new(100, 0), Decimal::new(100, 0)));
+        assert!(!strict.allows(Decimal::new(100, 0), Decimal::new(101, 0)));
+        assert!(!strict.allows(Decimal::ZERO, Decimal::new(1, 0)));
+    }
+
+    #[test]
+    fn test_overdraft_policy_allows_up_to_limit() {
+        let overdraft = policy(OverdraftPolicy::Overdraft, 50);
+        assert!(overdraft.allows(Decimal::new(10, 0), Decimal::new(60, 0)));
+        assert!(!overdraft.allows(Decimal::new(10, 0), Decimal::new(61, 0)));
+        assert!(!overdraft.allows(Decimal::new(-50, 0), Decimal::new(1, 0)));
+    }
+
+    #[test]
+    fn test_unlimited_policy_allows_any_amount() {
+        let unlimited = policy(OverdraftPolicy::Unlimited, 0);
+        assert!(unlimited.allows(Decimal::new(-1_000_000, 0), Decimal::new(1_000_000, 0)));
+    }
+
+    #[test]
+    fn test_limit_only_valid_for_overdraft() {
+        assert!(policy(OverdraftPolicy::Overdraft, 50).validate().is_empty());
+        assert_eq!(policy(OverdraftPolicy::Strict, 50).validate().len(), 1);
+        assert_eq!(policy(OverdraftPolicy::Overdraft, -1).validate().len(), 1);
+    }
+}
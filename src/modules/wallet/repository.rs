@@ -0,0 +1,293 @@
+use crate::modules::wallet::policy::AccountPolicy;
+use crate::modules::wallet::response::{AssetBalance, NegativeBalance};
+use rust_decimal::Decimal;
+use sqlx::{PgExecutor, PgPool};
+use time::PrimitiveDateTime;
+
+/// Materialized `asset` balance of `address`, or `None` when it has never
+/// been part of a transaction in that asset.
+pub async fn get_stored_balance<'e, E>(
+    executor: E,
+    address: &str,
+    asset: &str,
+) -> Result<Option<Decimal>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let balance =
+        sqlx::query_scalar("SELECT balance FROM balances WHERE address = $1 AND asset = $2")
+            .bind(address)
+            .bind(asset)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(balance)
+}
+
+/// Materialized balances of `address` in every asset it holds.
+pub(crate) async fn get_stored_balances(
+    pool: &PgPool,
+    address: &str,
+) -> Result<Vec<AssetBalance>, sqlx::Error> {
+    let balances = sqlx::query_as::<_, AssetBalance>(
+        "SELECT asset, balance FROM balances WHERE address = $1 ORDER BY asset",
+    )
+    .bind(address)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(balances)
+}
+
+/// Applies a posting of `amount` to the materialized `asset` balance of
+/// `address`.
+pub(crate) async fn add_to_balance<'e, E>(
+    executor: E,
+    address: &str,
+    asset: &str,
+    amount: Decimal,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "INSERT INTO balances (address, asset, balance) VALUES ($1, $2, $3)
+         ON CONFLICT (address, asset) DO UPDATE
+         SET balance = balances.balance + EXCLUDED.balance,
+             updated_at = NOW()",
+    )
+    .bind(address)
+    .bind(asset)
+    .bind(amount)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Confirmed `asset` balance of `address` minus what its pending
+/// transactions are about to send and what its unexpired holds reserve.
+pub async fn get_available_balance<'e, E>(
+    executor: E,
+    address: &str,
+    asset: &str,
+) -> Result<Decimal, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let available: Decimal = sqlx::query_scalar(
+        "SELECT COALESCE((SELECT balance FROM balances WHERE address = $1 AND asset = $2), 0)
+              - COALESCE((SELECT SUM(amount) FROM transactions
+                          WHERE address_from = $1 AND asset = $2 AND status = 'pending'), 0)
+              - COALESCE((SELECT SUM(amount) FROM holds
+                          WHERE address = $1 AND asset = $2
+                            AND status = 'active' AND expires_at > NOW()), 0)",
+    )
+    .bind(address)
+    .bind(asset)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(available)
+}
+
+/// Recomputes every materialized balance from the confirmed rows of the
+/// `transactions` table and returns the number of balances written. Writers
+/// are blocked until the rebuild commits, so no posting can be lost in
+/// between.
+pub(crate) async fn rebuild_balances(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+
+    sqlx::query("LOCK TABLE balances IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *db_tx)
+        .await?;
+
+    sqlx::query("DELETE FROM balances")
+        .execute(&mut *db_tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO accounts (address)
+         SELECT address_from FROM transactions
+         UNION
+         SELECT address_to FROM transactions
+         ON CONFLICT DO NOTHING",
+    )
+    .execute(&mut *db_tx)
+    .await?;
+
+    let rebuilt = sqlx::query(
+        "INSERT INTO balances (address, asset, balance)
+         SELECT entry.address, t.asset, SUM(entry.amount)
+         FROM transactions t
+         CROSS JOIN LATERAL (VALUES (t.address_from, -t.amount), (t.address_to, t.amount))
+             AS entry (address, amount)
+         WHERE t.status = 'confirmed'
+         GROUP BY entry.address, t.asset",
+    )
+    .execute(&mut *db_tx)
+    .await?
+    .rows_affected();
+
+    db_tx.commit().await?;
+
+    Ok(rebuilt)
+}
+
+/// Up to `limit` randomly chosen materialized balances as
+/// `(address, asset, balance)`.
+pub(crate) async fn sample_stored_balances(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<(String, String, Decimal)>, sqlx::Error> {
+    let balances =
+        sqlx::query_as("SELECT address, asset, balance FROM balances ORDER BY random() LIMIT $1")
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(balances)
+}
+
+/// Inflow, outflow and closing `asset` balance of `address` for every
+/// `interval` bucket between `from` and `to`, including buckets without
+/// transactions.
+pub(crate) async fn get_balance_history_buckets(
+    pool: &PgPool,
+    address: &str,
+    asset: &str,
+    interval: &str,
+    from: PrimitiveDateTime,
+    to: PrimitiveDateTime,
+) -> Result<Vec<(PrimitiveDateTime, Decimal, Decimal, Decimal)>, sqlx::Error> {
+    let history = sqlx::query_as(
+        "WITH movements AS (
+             SELECT date_trunc($2, created_at) AS bucket,
+                    CASE WHEN address_to = $1 THEN amount ELSE 0 END AS inflow,
+                    CASE WHEN address_from = $1 THEN amount ELSE 0 END AS outflow
+             FROM transactions
+             WHERE (address_from = $1 OR address_to = $1)
+               AND asset = $5
+               AND status = 'confirmed'
+               AND created_at < date_trunc($2, $4) + ('1 ' || $2)::INTERVAL
+         ),
+         opening AS (
+             SELECT COALESCE(SUM(inflow - outflow), 0) AS balance
+             FROM movements
+             WHERE bucket < date_trunc($2, $3)
+         ),
+         flows AS (
+             SELECT bucket, SUM(inflow) AS inflow, SUM(outflow) AS outflow
+             FROM movements
+             WHERE bucket >= date_trunc($2, $3)
+             GROUP BY bucket
+         ),
+         buckets AS (
+             SELECT generate_series(date_trunc($2, $3), date_trunc($2, $4), ('1 ' || $2)::INTERVAL)
+                 AS bucket
+         )
+         SELECT b.bucket,
+                COALESCE(f.inflow, 0) AS inflow,
+                COALESCE(f.outflow, 0) AS outflow,
+                (SELECT balance FROM opening)
+                    + SUM(COALESCE(f.inflow, 0) - COALESCE(f.outflow, 0))
+                        OVER (ORDER BY b.bucket) AS closing_balance
+         FROM buckets b
+         LEFT JOIN flows f ON f.bucket = b.bucket
+         ORDER BY b.bucket",
+    )
+    .bind(address)
+    .bind(interval)
+    .bind(from)
+    .bind(to)
+    .bind(asset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(history)
+}
+
+/// Overdraft policy of `address`, or `None` when no account exists for it yet.
+pub async fn get_account_policy<'e, E>(
+    executor: E,
+    address: &str,
+) -> Result<Option<AccountPolicy>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let policy = sqlx::query_as::<_, AccountPolicy>(
+        "SELECT overdraft_policy, overdraft_limit FROM accounts WHERE address = $1",
+    )
+    .bind(address)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(policy)
+}
+
+/// Sets the overdraft policy of `address`, opening the account if needed.
+pub(crate) async fn set_account_policy(
+    pool: &PgPool,
+    address: &str,
+    policy: &AccountPolicy,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO accounts (address, overdraft_policy, overdraft_limit) VALUES ($1, $2, $3)
+         ON CONFLICT (address) DO UPDATE
+         SET overdraft_policy = EXCLUDED.overdraft_policy,
+             overdraft_limit = EXCLUDED.overdraft_limit",
+    )
+    .bind(address)
+    .bind(policy.policy)
+    .bind(policy.overdraft_limit)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Balances of every address owned by user `id`, as `(address, balance)`
+/// pairs ordered by address and asset. Addresses without any balance are
+/// listed once with `None`.
+pub(crate) async fn get_owned_balances(
+    pool: &PgPool,
+    id: i32,
+) -> Result<Vec<(String, Option<AssetBalance>)>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, Option<String>, Option<Decimal>)>(
+        "SELECT a.address, b.asset, b.balance
+         FROM accounts a
+         LEFT JOIN balances b ON b.address = a.address
+         WHERE a.user_id = $1
+         ORDER BY a.address, b.asset",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(address, asset, balance)| {
+            let balance = asset
+                .zip(balance)
+                .map(|(asset, balance)| AssetBalance { asset, balance });
+            (address, balance)
+        })
+        .collect())
+}
+
+/// Every balance below zero, lowest first.
+pub(crate) async fn get_negative_balances(
+    pool: &PgPool,
+) -> Result<Vec<NegativeBalance>, sqlx::Error> {
+    let balances = sqlx::query_as::<_, NegativeBalance>(
+        "SELECT b.address, b.asset, b.balance, a.overdraft_policy, a.overdraft_limit
+         FROM balances b
+         JOIN accounts a ON a.address = b.address
+         WHERE b.balance < 0
+         ORDER BY b.balance, b.address, b.asset",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(balances)
+}
@@ -0,0 +1,103 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::transactions::request::default_asset;
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BalanceCheckQuery {
+    pub sample: Option<i64>,
+}
+
+/// Query string of the balance endpoint. `at` is RFC 3339; `through_id` breaks
+/// ties between transactions recorded at exactly `at`. Without `asset`, the
+/// balance of every asset is returned.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BalanceQuery {
+    pub asset: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub at: Option<OffsetDateTime>,
+    pub through_id: Option<i32>,
+}
+
+/// Query string of endpoints that report on a single asset.
+#[derive(Debug, Deserialize)]
+pub(crate) struct AssetQuery {
+    #[serde(default = "default_asset")]
+    pub asset: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Interval {
+    Hour,
+    Day,
+    Week,
+}
+
+impl Interval {
+    /// Field name understood by PostgreSQL's `date_trunc`.
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Interval::Hour => "hour",
+            Interval::Day => "day",
+            Interval::Week => "week",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Interval::Hour => Duration::HOUR,
+            Interval::Day => Duration::DAY,
+            Interval::Week => Duration::WEEK,
+        }
+    }
+}
+
+/// Query string of the balance history endpoint. `from` and `to` are RFC 3339
+/// and default to the [`DEFAULT_HISTORY_BUCKETS`] intervals ending now.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BalanceHistoryQuery {
+    pub interval: Interval,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+pub(crate) const DEFAULT_HISTORY_BUCKETS: i32 = 30;
+pub(crate) const MAX_HISTORY_BUCKETS: i64 = 1_000;
+
+impl BalanceHistoryQuery {
+    /// Resolves the requested range, rejecting ranges that are inverted or
+    /// would produce more than [`MAX_HISTORY_BUCKETS`] buckets.
+    pub fn range(&self) -> Result<(OffsetDateTime, OffsetDateTime), FieldError> {
+        let to = self.to.unwrap_or_else(OffsetDateTime::now_utc);
+        let from = self
+            .from
+            .unwrap_or(to - self.interval.duration() * DEFAULT_HISTORY_BUCKETS);
+
+        if from > to {
+            return Err(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "from",
+                "from cannot be later than to.",
+            ));
+        }
+        if (to - from).whole_seconds() / self.interval.duration().whole_seconds()
+            >= MAX_HISTORY_BUCKETS
+        {
+            return Err(FieldError::new(
+                ErrorCode::InvalidParameter,
+                "from",
+                format!(
+                    "The requested range cannot span more than {} intervals.",
+                    MAX_HISTORY_BUCKETS
+                ),
+            ));
+        }
+
+        Ok((from, to))
+    }
+}
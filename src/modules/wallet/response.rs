@@ -0,0 +1,54 @@
+use crate::modules::wallet::policy::OverdraftPolicy;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// Outcome of comparing materialized balances with balances recomputed from
+/// transaction history.
+#[derive(Debug, Serialize)]
+pub struct BalanceCheck {
+    pub checked: usize,
+    pub mismatches: Vec<BalanceMismatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceMismatch {
+    pub address: String,
+    pub asset: String,
+    pub stored: Decimal,
+    pub computed: Decimal,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AssetBalance {
+    pub asset: String,
+    pub balance: Decimal,
+}
+
+/// One of the caller's addresses and its balances.
+#[derive(Debug, Serialize)]
+pub struct WalletAddress {
+    pub address: String,
+    pub balances: Vec<AssetBalance>,
+}
+
+/// Flows of one history bucket and the balance once the bucket has closed.
+#[derive(Debug, Serialize)]
+pub struct BalanceHistoryPoint {
+    #[serde(with = "time::serde::rfc3339")]
+    pub bucket: OffsetDateTime,
+    pub inflow: Decimal,
+    pub outflow: Decimal,
+    pub closing_balance: Decimal,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct NegativeBalance {
+    pub address: String,
+    pub asset: String,
+    pub balance: Decimal,
+    #[sqlx(rename = "overdraft_policy")]
+    pub policy: OverdraftPolicy,
+    pub overdraft_limit: Decimal,
+}
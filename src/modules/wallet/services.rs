@@ -1,74 +1,285 @@
 use crate::api::build_json_response;
-use crate::modules::transactions::TransactionType;
-use crate::modules::transactions::repository::get_transactions_by_address;
+use crate::api::error::{ApiError, ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::api_keys::response::ApiKey;
+use crate::modules::transactions::TransactionStatus;
+use crate::modules::transactions::repository::{
+    TransactionRepository, get_transactions_by_address,
+};
+use crate::modules::transactions::request::to_utc_primitive;
 use crate::modules::transactions::response::Transaction;
+use crate::modules::wallet::policy::AccountPolicy;
+use crate::modules::wallet::repository::{
+    get_account_policy, get_available_balance, get_balance_history_buckets, get_negative_balances,
+    get_owned_balances, sample_stored_balances, set_account_policy,
+};
+use crate::modules::wallet::request::{
+    AssetQuery, BalanceCheckQuery, BalanceHistoryQuery, BalanceQuery,
+};
+use crate::modules::wallet::response::{
+    AssetBalance, BalanceCheck, BalanceHistoryPoint, BalanceMismatch, WalletAddress,
+};
 use actix_web::http::StatusCode;
-use actix_web::{HttpRequest, HttpResponse, Responder, get, web};
+use actix_web::{HttpResponse, get, put, web};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_BALANCE_CHECK_SAMPLE: i64 = 100;
+pub const MAX_BALANCE_CHECK_SAMPLE: i64 = 10_000;
+
+/// Addresses owned by the caller's user, with their balances. Keys that do
+/// not act for a user own nothing.
+#[get("")]
+pub async fn get_wallet(
+    pool: web::Data<PgPool>,
+    api_key: Option<web::ReqData<ApiKey>>,
+) -> Result<HttpResponse, ApiError> {
+    let Some(user_id) = api_key.and_then(|api_key| api_key.user_id) else {
+        return Ok(build_json_response(
+            Vec::<WalletAddress>::new(),
+            StatusCode::OK,
+        ));
+    };
+
+    let mut wallet: Vec<WalletAddress> = vec![];
+    for (address, balance) in get_owned_balances(&pool, user_id).await? {
+        match wallet.last_mut() {
+            Some(last) if last.address == address => last.balances.extend(balance),
+            _ => wallet.push(WalletAddress {
+                address,
+                balances: balance.into_iter().collect(),
+            }),
+        }
+    }
+
+    Ok(build_json_response(wallet, StatusCode::OK))
+}
 
 #[get("/balance/{address}")]
-pub async fn get_balance(req: HttpRequest, path: web::Path<String>) -> impl Responder {
-    let pool = match req.app_data::<web::Data<PgPool>>() {
-        Some(pool) => pool,
-        None => {
-            return HttpResponse::InternalServerError().json("Database pool not found");
+pub async fn get_balance(
+    repository: web::Data<dyn TransactionRepository>,
+    path: web::Path<String>,
+    query: web::Query<BalanceQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+    let query = query.into_inner();
+
+    if query.at.is_none() && query.through_id.is_some() {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            ErrorCode::InvalidParameter,
+            "through_id",
+            "through_id requires at.",
+        )]));
+    }
+
+    if let Some(asset) = &query.asset
+        && repository.find_asset(asset).await?.is_none()
+    {
+        return Err(ApiError::not_found(
+            ErrorCode::AssetNotFound,
+            "Asset not found.",
+        ));
+    }
+
+    let balances = repository
+        .balances(
+            &address,
+            query.asset.as_deref(),
+            query.at.map(to_utc_primitive),
+            query.through_id,
+        )
+        .await?;
+
+    match query.asset {
+        Some(asset) => {
+            let balance = balances
+                .into_iter()
+                .find(|balance| balance.asset == asset)
+                .map_or(Decimal::ZERO, |balance| balance.balance);
+            Ok(build_json_response(balance, StatusCode::OK))
         }
-    };
+        None => Ok(build_json_response(balances, StatusCode::OK)),
+    }
+}
+
+/// Balance of `?asset=` minus pending outgoing transactions and active holds.
+#[get("/balance/{address}/available")]
+pub async fn get_available_balance_report(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<AssetQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let balance = get_available_balance(
+        pool.get_ref(),
+        &address::canonical(&path.into_inner()),
+        &query.asset,
+    )
+    .await?;
+
+    Ok(build_json_response(balance, StatusCode::OK))
+}
+
+#[get("/balance/{address}/history")]
+pub async fn get_balance_history(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<BalanceHistoryQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+    let (from, to) = query
+        .range()
+        .map_err(|error| ApiError::Validation(vec![error]))?;
+
+    let rows = get_balance_history_buckets(
+        &pool,
+        &address,
+        &query.asset,
+        query.interval.as_sql(),
+        to_utc_primitive(from),
+        to_utc_primitive(to),
+    )
+    .await?;
+
+    let history: Vec<BalanceHistoryPoint> = rows
+        .into_iter()
+        .map(
+            |(bucket, inflow, outflow, closing_balance)| BalanceHistoryPoint {
+                bucket: bucket.assume_utc(),
+                inflow,
+                outflow,
+                closing_balance,
+            },
+        )
+        .collect();
+    Ok(build_json_response(history, StatusCode::OK))
+}
+
+#[get("/balances/check")]
+pub async fn check_balances(
+    pool: web::Data<PgPool>,
+    query: web::Query<BalanceCheckQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let sample = query.sample.unwrap_or(DEFAULT_BALANCE_CHECK_SAMPLE);
+    if !(1..=MAX_BALANCE_CHECK_SAMPLE).contains(&sample) {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            ErrorCode::InvalidParameter,
+            "sample",
+            format!("Sample must be between 1 and {}.", MAX_BALANCE_CHECK_SAMPLE),
+        )]));
+    }
+
+    let check = verify_stored_balances(&pool, sample).await?;
+    Ok(build_json_response(check, StatusCode::OK))
+}
+
+#[get("/balances/negative")]
+pub async fn get_negative_balances_report(
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let balances = get_negative_balances(&pool).await?;
+    Ok(build_json_response(balances, StatusCode::OK))
+}
+
+#[get("/policy/{address}")]
+pub async fn get_policy(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+
+    let policy = get_account_policy(pool.get_ref(), &address).await?;
+    Ok(build_json_response(
+        policy.unwrap_or_default(),
+        StatusCode::OK,
+    ))
+}
 
-    let address = path.into_inner();
+#[put("/policy/{address}")]
+pub async fn update_policy(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    body: web::Json<AccountPolicy>,
+) -> Result<HttpResponse, ApiError> {
+    let address = address::canonical(&path.into_inner());
+    let policy = body.into_inner();
 
-    let transactions = match get_transactions_by_address(pool, &address).await {
-        Ok(transactions) => transactions,
-        Err(err) => {
-            eprintln!("Failed to fetch transactions: {}", err);
+    let errors = policy.validate();
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
 
-            return if err.downcast_ref::<sqlx::Error>().is_some() {
-                HttpResponse::InternalServerError().json("Database error occurred")
-            } else {
-                HttpResponse::InternalServerError().json("An unexpected error occurred")
-            };
+    set_account_policy(&pool, &address, &policy).await?;
+    Ok(build_json_response(policy, StatusCode::OK))
+}
+
+/// Compares the materialized balance of up to `sample` random addresses with
+/// [`calculate_balance`] over their full history.
+pub async fn verify_stored_balances(
+    pool: &PgPool,
+    sample: i64,
+) -> Result<BalanceCheck, sqlx::Error> {
+    let stored_balances = sample_stored_balances(pool, sample).await?;
+    let mut mismatches = vec![];
+
+    for (address, asset, stored) in &stored_balances {
+        let stored = *stored;
+        let transactions: Vec<Transaction> = get_transactions_by_address(pool, address)
+            .await?
+            .into_iter()
+            .filter(|tx| &tx.asset == asset)
+            .collect();
+        let computed = calculate_balance(address, &transactions);
+
+        if stored != computed {
+            mismatches.push(BalanceMismatch {
+                address: address.clone(),
+                asset: asset.clone(),
+                stored,
+                computed,
+            });
         }
-    };
+    }
 
-    let balance = calculate_balance(&address, &transactions);
+    Ok(BalanceCheck {
+        checked: stored_balances.len(),
+        mismatches,
+    })
+}
 
-    build_json_response(balance, StatusCode::OK)
+/// Balance of `address` in every asset it has transacted in, computed with
+/// [`calculate_balance`].
+pub fn calculate_balances(address: &str, transactions: &[Transaction]) -> Vec<AssetBalance> {
+    let mut by_asset: BTreeMap<&str, Vec<Transaction>> = BTreeMap::new();
+    for tx in transactions {
+        by_asset.entry(&tx.asset).or_default().push(tx.clone());
+    }
+
+    by_asset
+        .into_iter()
+        .map(|(asset, transactions)| AssetBalance {
+            asset: asset.to_string(),
+            balance: calculate_balance(address, &transactions),
+        })
+        .collect()
 }
 
+/// Balance of `address` as the sum of the postings of the confirmed
+/// transactions in `transactions`, which must all be in the same asset.
 pub fn calculate_balance(address: &str, transactions: &[Transaction]) -> Decimal {
-    let balance = transactions
+    transactions
         .iter()
-        .fold(Decimal::new(0, 0), |mut balance, tx| {
-            match tx.transaction_type {
-                TransactionType::Deposit => {
-                    if tx.address_to == address {
-                        balance += tx.amount;
-                    } else {
-                        balance -= tx.amount;
-                    }
-                }
-                TransactionType::Withdrawal => {
-                    if tx.address_from == address {
-                        balance -= tx.amount;
-                    } else {
-                        balance += tx.amount;
-                    }
-                }
-            }
-            balance
-        });
-
-    if balance < Decimal::ZERO {
-        Decimal::ZERO
-    } else {
-        balance
-    }
+        .filter(|tx| tx.status == TransactionStatus::Confirmed)
+        .flat_map(Transaction::postings)
+        .filter(|posting| posting.account == address)
+        .map(|posting| posting.amount)
+        .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::modules::transactions::TransactionType;
     use rust_decimal::Decimal;
     use time::{PrimitiveDateTime, format_description};
 
@@ -93,14 +304,21 @@ mod tests {
             address_from: from.to_string(),
             address_to: to.to_string(),
             amount: Decimal::new(amount, 0),
+            asset: "ETH".to_string(),
             transaction_type,
             created_at: created_at_parsed,
+            reversal_of: None,
+            reversed_by: None,
+            status: TransactionStatus::Confirmed,
+            api_key_id: None,
+            nonce: None,
+            settled_by_api_key_id: None,
         }
     }
 
     #[test]
     fn test_empty_transactions() {
-        assert_eq!(calculate_balance(MY_ADDRESS, &vec![]), Decimal::new(0, 0));
+        assert_eq!(calculate_balance(MY_ADDRESS, &[]), Decimal::new(0, 0));
     }
 
     #[test]
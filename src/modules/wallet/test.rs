@@ -1,6 +1,24 @@
-use crate::modules::transactions::TransactionType;
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::assets::Asset;
+use crate::modules::events::EventBus;
+use crate::modules::transactions;
+use crate::modules::transactions::repository::validate_and_insert;
 use crate::modules::transactions::response::Transaction;
+use crate::modules::transactions::test::{
+    issuer, pg_repository, test_config, test_pool, unique_address, unlimited,
+};
+use crate::modules::transactions::{TransactionStatus, TransactionType};
+use crate::modules::wallet::policy::AccountPolicy;
+use crate::modules::wallet::services::calculate_balance;
+use crate::modules::wallet::{api_config, ledger_config};
+use actix_web::http::StatusCode;
+use actix_web::test::{TestRequest, call_and_read_body_json, call_service, init_service};
+use actix_web::{App, web};
 use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+use time::PrimitiveDateTime;
+use time::macros::datetime;
 
 fn deposit(from: &str, to: &str, amount: i64) -> Transaction {
     Transaction {
@@ -8,19 +26,77 @@ fn deposit(from: &str, to: &str, amount: i64) -> Transaction {
         address_from: from.to_string(),
         address_to: to.to_string(),
         amount: Decimal::new(amount, 0),
+        asset: "ETH".to_string(),
         transaction_type: TransactionType::Deposit,
         created_at: None,
+        reversal_of: None,
+        reversed_by: None,
+        status: TransactionStatus::Confirmed,
+        api_key_id: None,
+        nonce: None,
+        settled_by_api_key_id: None,
     }
 }
 
+/// Records `transaction` as if it had been created at `created_at`, with its
+/// postings and stored balances, as the API would.
+async fn insert_at(
+    pool: &PgPool,
+    mut transaction: Transaction,
+    created_at: PrimitiveDateTime,
+) -> i32 {
+    transaction.created_at = Some(created_at);
+    let mut conn = pool.acquire().await.unwrap();
+
+    validate_and_insert(&mut conn, transaction)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+fn eth() -> Asset {
+    Asset {
+        symbol: "ETH".to_string(),
+        decimals: 18,
+        contract_address: None,
+    }
+}
+
+/// Validates `tx` the way a strict account would, against the balance left
+/// by `history`.
+fn validate(tx: &Transaction, history: &[Transaction]) -> Vec<FieldError> {
+    let balance = calculate_balance(&tx.address_from, history);
+
+    tx.validate(balance, &AccountPolicy::default(), Some(&eth()))
+}
+
+/// Validates `tx` as sent from an empty issuer account.
+fn validate_from_issuer(tx: &Transaction) -> Vec<FieldError> {
+    tx.validate(Decimal::ZERO, &unlimited(), Some(&eth()))
+}
+
+/// Whether `errors` reports `code` against `field`.
+fn has_error(errors: &[FieldError], code: ErrorCode, field: &str) -> bool {
+    errors
+        .iter()
+        .any(|error| error.code == code && error.field == Some(field))
+}
+
 fn withdrawal(from: &str, to: &str, amount: i64) -> Transaction {
     Transaction {
         id: None,
         address_from: from.to_string(),
         address_to: to.to_string(),
         amount: Decimal::new(amount, 0),
+        asset: "ETH".to_string(),
         transaction_type: TransactionType::Withdrawal,
         created_at: None,
+        reversal_of: None,
+        reversed_by: None,
+        status: TransactionStatus::Confirmed,
+        api_key_id: None,
+        nonce: None,
+        settled_by_api_key_id: None,
     }
 }
 
@@ -31,7 +107,7 @@ fn test_valid_deposit_transaction() {
         "0xBBB2222222222222222222222222222222222222",
         100,
     );
-    let errors = tx.validate(&[]);
+    let errors = validate_from_issuer(&tx);
     assert!(errors.is_empty());
 }
 
@@ -47,7 +123,7 @@ fn test_valid_withdrawal_with_sufficient_balance() {
         "0xBBB2222222222222222222222222222222222222",
         150,
     );
-    let errors = tx.validate(&history);
+    let errors = validate(&tx, &history);
     assert!(errors.is_empty());
 }
 
@@ -63,9 +139,9 @@ fn test_withdrawal_insufficient_balance() {
         "0xBBB2222222222222222222222222222222222222",
         100,
     );
-    let errors = tx.validate(&history);
+    let errors = validate(&tx, &history);
     assert_eq!(errors.len(), 1);
-    assert!(errors.contains(&"Insufficient balance".to_string()));
+    assert!(has_error(&errors, ErrorCode::InsufficientBalance, "amount"));
 }
 
 #[test]
@@ -75,9 +151,9 @@ fn test_same_source_and_destination_address() {
         "0xAAA1111111111111111111111111111111111111",
         100,
     );
-    let errors = tx.validate(&[]);
+    let errors = validate_from_issuer(&tx);
     assert_eq!(errors.len(), 1);
-    assert!(errors.contains(&"Source and destination addresses cannot be the same.".to_string()));
+    assert!(has_error(&errors, ErrorCode::SameAddress, "address_to"));
 }
 
 #[test]
@@ -87,9 +163,13 @@ fn test_invalid_source_address() {
         "0xBBB2222222222222222222222222222222222222",
         100,
     );
-    let errors = tx.validate(&[]);
+    let errors = validate(&tx, &[]);
     assert_eq!(errors.len(), 1);
-    assert!(errors.contains(&"Invalid source address format.".to_string()));
+    assert!(has_error(
+        &errors,
+        ErrorCode::InvalidAddress,
+        "address_from"
+    ));
 }
 
 #[test]
@@ -99,18 +179,22 @@ fn test_invalid_destination_address() {
         "invalid_address",
         100,
     );
-    let errors = tx.validate(&[]);
+    let errors = validate_from_issuer(&tx);
     assert_eq!(errors.len(), 1);
-    assert!(errors.contains(&"Invalid destination address format.".to_string()));
+    assert!(has_error(&errors, ErrorCode::InvalidAddress, "address_to"));
 }
 
 #[test]
 fn test_invalid_both_addresses() {
     let tx = withdrawal("invalid", "invalid", 100);
-    let errors = tx.validate(&[]);
-    assert!(errors.contains(&"Invalid source address format.".to_string()));
-    assert!(errors.contains(&"Invalid destination address format.".to_string()));
-    assert!(errors.contains(&"Source and destination addresses cannot be the same.".to_string()));
+    let errors = validate(&tx, &[]);
+    assert!(has_error(
+        &errors,
+        ErrorCode::InvalidAddress,
+        "address_from"
+    ));
+    assert!(has_error(&errors, ErrorCode::InvalidAddress, "address_to"));
+    assert!(has_error(&errors, ErrorCode::SameAddress, "address_to"));
 }
 
 #[test]
@@ -120,8 +204,8 @@ fn test_zero_amount_deposit() {
         "0xBBB2222222222222222222222222222222222222",
         0,
     );
-    let errors = tx.validate(&[]);
-    assert!(errors.contains(&"Transaction amount must be greater than zero.".to_string()));
+    let errors = validate(&tx, &[]);
+    assert!(has_error(&errors, ErrorCode::InvalidAmount, "amount"));
 }
 
 #[test]
@@ -131,28 +215,349 @@ fn test_negative_amount_withdrawal() {
         "0xBBB2222222222222222222222222222222222222",
         -100,
     );
-    let errors = tx.validate(&[]);
-    assert!(errors.contains(&"Transaction amount must be greater than zero.".to_string()));
+    let errors = validate(&tx, &[]);
+    assert!(has_error(&errors, ErrorCode::InvalidAmount, "amount"));
 }
 
 #[test]
 fn test_multiple_errors_combined() {
     let tx = withdrawal("invalid", "invalid", 0);
-    let errors = tx.validate(&[]);
+    let errors = validate(&tx, &[]);
     assert_eq!(errors.len(), 4);
-    assert!(errors.contains(&"Invalid source address format.".to_string()));
-    assert!(errors.contains(&"Invalid destination address format.".to_string()));
-    assert!(errors.contains(&"Transaction amount must be greater than zero.".to_string()));
-    assert!(errors.contains(&"Source and destination addresses cannot be the same.".to_string()));
+    assert!(has_error(
+        &errors,
+        ErrorCode::InvalidAddress,
+        "address_from"
+    ));
+    assert!(has_error(&errors, ErrorCode::InvalidAddress, "address_to"));
+    assert!(has_error(&errors, ErrorCode::InvalidAmount, "amount"));
+    assert!(has_error(&errors, ErrorCode::SameAddress, "address_to"));
+}
+
+#[test]
+fn test_deposit_checks_the_source_balance() {
+    let tx = deposit(
+        "0xAAA1111111111111111111111111111111111111",
+        "0xBBB2222222222222222222222222222222222222",
+        100,
+    );
+    let errors = validate(&tx, &[]);
+    assert_eq!(errors.len(), 1);
+    assert!(has_error(&errors, ErrorCode::InsufficientBalance, "amount"));
 }
 
 #[test]
-fn test_deposit_does_not_check_balance() {
+fn test_issuer_deposit_does_not_check_balance() {
     let tx = deposit(
         "0xAAA1111111111111111111111111111111111111",
         "0xBBB2222222222222222222222222222222222222",
         1_000_000,
     );
-    let errors = tx.validate(&[]);
+    let errors = validate_from_issuer(&tx);
     assert!(errors.is_empty());
 }
+
+#[actix_web::test]
+async fn test_balance_at_point_in_time() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(20);
+    let other = issuer(&pool, 21).await;
+
+    let mut ids = vec![];
+    for (from, to, amount, created_at) in [
+        (&other, &address, 100, datetime!(2024-01-31 23:00:00)),
+        (&address, &other, 30, datetime!(2024-02-01 00:00:00)),
+        (&other, &address, 5, datetime!(2024-02-01 00:00:00)),
+        (&other, &address, 50, datetime!(2024-03-01 00:00:00)),
+    ] {
+        ids.push(insert_at(&pool, deposit(from, to, amount), created_at).await);
+    }
+
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/wallet").configure(api_config)),
+    )
+    .await;
+
+    for (query, expected) in [
+        ("at=2024-01-31T22:59:59Z".to_string(), 0),
+        ("at=2024-01-31T23:00:00Z".to_string(), 100),
+        ("at=2024-02-01T00:00:00Z".to_string(), 75),
+        (format!("at=2024-02-01T00:00:00Z&through_id={}", ids[1]), 70),
+        ("at=2024-02-01T01:00:00%2B01:00".to_string(), 75),
+        ("at=2030-01-01T00:00:00Z".to_string(), 125),
+    ] {
+        let request = TestRequest::get()
+            .uri(&format!(
+                "/api/wallet/balance/{}?asset=ETH&{}",
+                address, query
+            ))
+            .to_request();
+        let balance: Decimal = call_and_read_body_json(&app, request).await;
+        assert_eq!(balance, Decimal::new(expected, 0), "{}", query);
+    }
+}
+
+#[actix_web::test]
+async fn test_balance_history_fills_empty_buckets() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(22);
+    let other = issuer(&pool, 23).await;
+
+    for (from, to, amount, created_at) in [
+        (&other, &address, 100, datetime!(2024-04-30 12:00:00)),
+        (&other, &address, 20, datetime!(2024-05-01 08:00:00)),
+        (&address, &other, 35, datetime!(2024-05-01 20:00:00)),
+        (&other, &address, 10, datetime!(2024-05-03 00:00:00)),
+        (&other, &address, 999, datetime!(2024-05-04 00:00:00)),
+    ] {
+        insert_at(&pool, deposit(from, to, amount), created_at).await;
+    }
+
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/wallet").configure(api_config)),
+    )
+    .await;
+
+    let request = TestRequest::get()
+        .uri(&format!(
+            "/api/wallet/balance/{}/history?interval=day&from=2024-05-01T00:00:00Z&to=2024-05-03T23:59:59Z",
+            address
+        ))
+        .to_request();
+    let history: serde_json::Value = call_and_read_body_json(&app, request).await;
+
+    let points: Vec<(&str, Decimal, Decimal, Decimal)> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|point| {
+            (
+                point["bucket"].as_str().unwrap(),
+                point["inflow"].as_str().unwrap().parse().unwrap(),
+                point["outflow"].as_str().unwrap().parse().unwrap(),
+                point["closing_balance"].as_str().unwrap().parse().unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        points,
+        vec![
+            (
+                "2024-05-01T00:00:00Z",
+                Decimal::new(20, 0),
+                Decimal::new(35, 0),
+                Decimal::new(85, 0)
+            ),
+            (
+                "2024-05-02T00:00:00Z",
+                Decimal::ZERO,
+                Decimal::ZERO,
+                Decimal::new(85, 0)
+            ),
+            (
+                "2024-05-03T00:00:00Z",
+                Decimal::new(10, 0),
+                Decimal::ZERO,
+                Decimal::new(95, 0)
+            ),
+        ]
+    );
+
+    let request = TestRequest::get()
+        .uri(&format!(
+            "/api/wallet/balance/{}/history?interval=hour&from=2020-01-01T00:00:00Z&to=2024-01-01T00:00:00Z",
+            address
+        ))
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_overdraft_policy_governs_withdrawals() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let address = unique_address(24);
+    let other = unique_address(25);
+
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/wallet").configure(api_config))
+            .service(web::scope("/api/ledger").configure(ledger_config))
+            .service(web::scope("/api/transactions").configure(transactions::api_config)),
+    )
+    .await;
+
+    let send = |amount: &str, transaction_type: &str| {
+        TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": address,
+                "address_to": other,
+                "amount": amount,
+                "transaction_type": transaction_type,
+            }))
+            .to_request()
+    };
+    let withdraw = |amount: &str| send(amount, "Withdrawal");
+
+    let response = call_service(&app, withdraw("10")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = TestRequest::put()
+        .uri(&format!("/api/wallet/policy/{}", address))
+        .set_json(json!({ "policy": "overdraft", "overdraft_limit": "25" }))
+        .to_request();
+    assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+
+    assert_eq!(
+        call_service(&app, withdraw("20")).await.status(),
+        StatusCode::CREATED
+    );
+    assert_eq!(
+        call_service(&app, withdraw("6")).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    // A deposit debits its source too, so it cannot get around the policy.
+    assert_eq!(
+        call_service(&app, send("6", "Deposit")).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let request = TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}?asset=ETH", address))
+        .to_request();
+    let balance: Decimal = call_and_read_body_json(&app, request).await;
+    assert_eq!(balance, Decimal::new(-20, 0));
+
+    let request = TestRequest::get()
+        .uri("/api/ledger/balances/negative")
+        .to_request();
+    let report: serde_json::Value = call_and_read_body_json(&app, request).await;
+    let entry = report
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["address"] == address.as_str())
+        .unwrap();
+    assert_eq!(entry["policy"], "overdraft");
+}
+
+#[actix_web::test]
+async fn test_balances_are_kept_per_asset() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 26).await;
+    let address = unique_address(27);
+    let other = unique_address(28);
+
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .service(web::scope("/api/wallet").configure(api_config))
+            .service(web::scope("/api/transactions").configure(transactions::api_config)),
+    )
+    .await;
+
+    let create = |from: &str, to: &str, amount: &str, asset: &str, transaction_type: &str| {
+        TestRequest::post()
+            .uri("/api/transactions")
+            .set_json(json!({
+                "address_from": from,
+                "address_to": to,
+                "amount": amount,
+                "asset": asset,
+                "transaction_type": transaction_type,
+            }))
+            .to_request()
+    };
+
+    for (amount, asset) in [("1.5", "ETH"), ("250.125", "USDC")] {
+        let response =
+            call_service(&app, create(&funder, &address, amount, asset, "Deposit")).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // The ETH balance cannot fund a USDC withdrawal.
+    assert_eq!(
+        call_service(&app, create(&address, &other, "251", "USDC", "Withdrawal"))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        call_service(
+            &app,
+            create(&address, &other, "0.0000001", "USDC", "Withdrawal")
+        )
+        .await
+        .status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        call_service(&app, create(&address, &other, "1", "NOPE", "Withdrawal"))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        call_service(&app, create(&address, &other, "50", "USDC", "Withdrawal"))
+            .await
+            .status(),
+        StatusCode::CREATED
+    );
+
+    let request = TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}", address))
+        .to_request();
+    let balances: serde_json::Value = call_and_read_body_json(&app, request).await;
+    assert_eq!(
+        balances,
+        json!([
+            { "asset": "ETH", "balance": "1.500000000000000000" },
+            { "asset": "USDC", "balance": "200.125000000000000000" },
+        ])
+    );
+
+    let request = TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}?asset=USDC", address))
+        .to_request();
+    let balance: Decimal = call_and_read_body_json(&app, request).await;
+    assert_eq!(balance, Decimal::new(200_125, 3));
+
+    let request = TestRequest::get()
+        .uri(&format!("/api/wallet/balance/{}?asset=NOPE", address))
+        .to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
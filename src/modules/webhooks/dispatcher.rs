@@ -0,0 +1,132 @@
+use crate::configurations::Webhooks;
+use crate::modules::webhooks::repository::{
+    claim_due_deliveries, fan_out_outbox, record_delivery_attempt,
+};
+use crate::modules::webhooks::response::DueDelivery;
+use crate::modules::webhooks::{DeliveryStatus, SIGNATURE_HEADER, sign_payload};
+use actix_web::rt::time::interval;
+use reqwest::Client;
+use sqlx::PgPool;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+/// Outbox events turned into deliveries per batch.
+const OUTBOX_BATCH: i64 = 100;
+/// Deliveries sent per batch.
+const DELIVERY_BATCH: i64 = 50;
+const MAX_BACKOFF_SECONDS: i64 = 86_400;
+
+/// Sends due webhook deliveries every `poll_interval_seconds`.
+pub async fn run(pool: PgPool, config: Webhooks) {
+    let client = match client(&config) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to build the webhook client: {}", e);
+            return;
+        }
+    };
+    let mut ticker = interval(Duration::from_secs(config.poll_interval_seconds));
+
+    loop {
+        ticker.tick().await;
+
+        match dispatch(&pool, &client, &config).await {
+            Ok(0) => {}
+            Ok(sent) => info!("Attempted {} webhook deliveries", sent),
+            Err(e) => error!("Failed to dispatch webhooks: {}", e),
+        }
+    }
+}
+
+/// HTTP client for deliveries, which gives up after `timeout_seconds`.
+pub fn client(config: &Webhooks) -> reqwest::Result<Client> {
+    Client::builder()
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .build()
+}
+
+/// Fans new outbox events out to their subscriptions, then makes one attempt
+/// at every due delivery. Returns how many attempts were made.
+pub async fn dispatch(
+    pool: &PgPool,
+    client: &Client,
+    config: &Webhooks,
+) -> Result<usize, sqlx::Error> {
+    while fan_out_outbox(pool, OUTBOX_BATCH).await? == OUTBOX_BATCH as u64 {}
+
+    // Claimed deliveries are left alone by other dispatchers until the
+    // request has had time to time out.
+    let lease_seconds = config.timeout_seconds as i64 * 2;
+    let deliveries = claim_due_deliveries(pool, DELIVERY_BATCH, lease_seconds).await?;
+
+    for delivery in &deliveries {
+        let (response_status, error) = send(client, delivery).await;
+        let attempts = delivery.attempts + 1;
+
+        let status = if response_status.is_some_and(|status| (200..300).contains(&status)) {
+            DeliveryStatus::Delivered
+        } else if attempts >= config.max_attempts {
+            DeliveryStatus::Failed
+        } else {
+            DeliveryStatus::Pending
+        };
+
+        record_delivery_attempt(
+            pool,
+            delivery.id,
+            response_status,
+            error.as_deref(),
+            status,
+            backoff(config.backoff_base_seconds, delivery.attempts),
+        )
+        .await?;
+    }
+
+    Ok(deliveries.len())
+}
+
+/// POSTs the delivery's payload, signed with its subscription's secret.
+/// Returns the response status, or why no response was received.
+async fn send(client: &Client, delivery: &DueDelivery) -> (Option<i16>, Option<String>) {
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+    let result = client
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", delivery.id.to_string())
+        .header("X-Webhook-Event", delivery.event_type.as_str())
+        .header(
+            SIGNATURE_HEADER,
+            sign_payload(&delivery.secret, timestamp, &delivery.payload),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match result {
+        Ok(response) => (Some(response.status().as_u16() as i16), None),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+/// Seconds to wait before retrying a delivery that has failed `attempts`
+/// times before: `base`, doubled after each further failure, up to a day.
+fn backoff(base: i64, attempts: i32) -> i64 {
+    base.saturating_mul(2i64.saturating_pow(attempts.max(0) as u32))
+        .min(MAX_BACKOFF_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_and_saturates() {
+        assert_eq!(backoff(10, 0), 10);
+        assert_eq!(backoff(10, 1), 20);
+        assert_eq!(backoff(10, 3), 80);
+        assert_eq!(backoff(10, 100), MAX_BACKOFF_SECONDS);
+        assert_eq!(backoff(0, 5), 0);
+    }
+}
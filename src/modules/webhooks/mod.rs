@@ -0,0 +1,111 @@
+use crate::modules::transactions::TransactionType;
+use crate::modules::webhooks::services::{
+    create_webhook, delete_webhook, list_deliveries, list_webhooks,
+};
+use actix_web::web;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub mod dispatcher;
+pub mod repository;
+mod request;
+pub mod response;
+mod services;
+
+#[cfg(test)]
+pub mod test;
+
+/// Header carrying [`sign_payload`]'s signature of each delivery.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_webhooks)
+        .service(create_webhook)
+        .service(delete_webhook)
+        .service(list_deliveries);
+}
+
+/// What a subscription is notified of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Deposit,
+    Withdrawal,
+}
+
+impl EventType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventType::Deposit => "deposit",
+            EventType::Withdrawal => "withdrawal",
+        }
+    }
+}
+
+impl From<TransactionType> for EventType {
+    fn from(transaction_type: TransactionType) -> Self {
+        match transaction_type {
+            TransactionType::Deposit => EventType::Deposit,
+            TransactionType::Withdrawal => EventType::Withdrawal,
+        }
+    }
+}
+
+/// Progress of one event towards one subscription. Delivered and failed are
+/// final.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// A new random secret shared with a subscriber to verify deliveries.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+/// Value of [`SIGNATURE_HEADER`]: `t=<timestamp>,v1=<signature>`, where the
+/// signature is the hex HMAC-SHA256 of `<timestamp>.<body>` keyed with the
+/// subscription's secret. Signing the timestamp lets receivers reject
+/// replayed deliveries.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_is_hmac_sha256_of_timestamp_and_body() {
+        assert_eq!(
+            sign_payload("whsec_test", 1_700_000_000, r#"{"type":"deposit"}"#),
+            "t=1700000000,v1=174d4e406b43355fe56237552d8c21fa9fc20250e3f1e3ade1c54b2555e2d24b"
+        );
+    }
+
+    #[test]
+    fn test_secrets_are_random() {
+        let secret = generate_secret();
+        assert!(secret.starts_with("whsec_"));
+        assert_eq!(secret.len(), "whsec_".len() + 64);
+        assert_ne!(secret, generate_secret());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::modules::webhooks::response::{
+    Delivery, DeliveryAttempt, DueDelivery, WebhookSubscription,
+};
+use crate::modules::webhooks::{DeliveryStatus, EventType};
+use sqlx::{PgExecutor, PgPool};
+
+const SUBSCRIPTION_COLUMNS: &str = "id, url, address, event_types, created_at";
+
+pub(crate) async fn insert_subscription(
+    pool: &PgPool,
+    url: &str,
+    address: &str,
+    event_types: &[EventType],
+    secret: &str,
+) -> Result<WebhookSubscription, sqlx::Error> {
+    let subscription = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "INSERT INTO webhook_subscriptions (url, address, event_types, secret)
+         VALUES ($1, $2, $3, $4)
+         RETURNING {}",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(url)
+    .bind(address)
+    .bind(event_types)
+    .bind(secret)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(subscription)
+}
+
+/// Subscriptions that have not been deleted.
+pub(crate) async fn get_subscriptions(
+    pool: &PgPool,
+) -> Result<Vec<WebhookSubscription>, sqlx::Error> {
+    let subscriptions = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "SELECT {} FROM webhook_subscriptions WHERE deleted_at IS NULL ORDER BY id",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subscriptions)
+}
+
+/// Subscription `id`, deleted or not.
+pub(crate) async fn get_subscription(
+    pool: &PgPool,
+    id: i32,
+) -> Result<Option<WebhookSubscription>, sqlx::Error> {
+    let subscription = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "SELECT {} FROM webhook_subscriptions WHERE id = $1",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(subscription)
+}
+
+/// Stops notifying subscription `id`. Its pending deliveries are abandoned.
+/// Returns `false` when there is no such subscription left.
+pub(crate) async fn set_subscription_deleted(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "UPDATE webhook_subscriptions SET deleted_at = NOW()
+         WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .execute(&mut *db_tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE webhook_deliveries SET status = 'failed'
+         WHERE subscription_id = $1 AND status = 'pending'",
+    )
+    .bind(id)
+    .execute(&mut *db_tx)
+    .await?;
+
+    db_tx.commit().await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Records an event for transaction `transaction_id` in the outbox. Runs in
+/// the database transaction that inserts the transaction, so the event is
+/// stored if and only if the transaction is.
+pub(crate) async fn insert_outbox_event<'e, E>(
+    executor: E,
+    transaction_id: i32,
+    event_type: EventType,
+    payload: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "INSERT INTO webhook_outbox (transaction_id, event_type, payload)
+         VALUES ($1, $2, $3::jsonb)",
+    )
+    .bind(transaction_id)
+    .bind(event_type)
+    .bind(payload)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Turns up to `limit` outbox events into one pending delivery per active
+/// subscription to their type and to either address of their transaction,
+/// and marks them processed. Concurrent dispatchers skip each other's events.
+pub(crate) async fn fan_out_outbox(pool: &PgPool, limit: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "WITH events AS (
+             SELECT id FROM webhook_outbox
+             WHERE processed_at IS NULL
+             ORDER BY id
+             LIMIT $1
+             FOR UPDATE SKIP LOCKED
+         ), deliveries AS (
+             INSERT INTO webhook_deliveries (subscription_id, outbox_id)
+             SELECT s.id, o.id
+             FROM events e
+             JOIN webhook_outbox o ON o.id = e.id
+             JOIN transactions t ON t.id = o.transaction_id
+             JOIN webhook_subscriptions s
+               ON s.deleted_at IS NULL
+              AND o.event_type = ANY (s.event_types)
+              AND s.address IN (t.address_from, t.address_to)
+             ON CONFLICT DO NOTHING
+         )
+         UPDATE webhook_outbox SET processed_at = NOW()
+         WHERE id IN (SELECT id FROM events)",
+    )
+    .bind(limit)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Claims up to `limit` due deliveries by pushing their next attempt
+/// `lease_seconds` ahead, so other dispatchers leave them alone while they
+/// are being sent. A dispatcher that dies mid-delivery only delays them.
+pub(crate) async fn claim_due_deliveries(
+    pool: &PgPool,
+    limit: i64,
+    lease_seconds: i64,
+) -> Result<Vec<DueDelivery>, sqlx::Error> {
+    let deliveries = sqlx::query_as::<_, DueDelivery>(
+        "UPDATE webhook_deliveries d
+         SET next_attempt_at = NOW() + make_interval(secs => $2)
+         FROM webhook_subscriptions s, webhook_outbox o
+         WHERE d.id IN (
+                 SELECT id FROM webhook_deliveries
+                 WHERE status = 'pending' AND next_attempt_at <= NOW()
+                 ORDER BY next_attempt_at
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+           AND s.id = d.subscription_id
+           AND o.id = d.outbox_id
+         RETURNING d.id, d.attempts, s.url, s.secret, o.event_type, o.payload::text AS payload",
+    )
+    .bind(limit)
+    .bind(lease_seconds as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deliveries)
+}
+
+/// Records an attempt at delivery `id` and moves it to `status`. A delivery
+/// left pending is retried `retry_in_seconds` from now.
+pub(crate) async fn record_delivery_attempt(
+    pool: &PgPool,
+    id: i64,
+    response_status: Option<i16>,
+    error: Option<&str>,
+    status: DeliveryStatus,
+    retry_in_seconds: i64,
+) -> Result<(), sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO webhook_delivery_attempts (delivery_id, response_status, error)
+         VALUES ($1, $2, $3)",
+    )
+    .bind(id)
+    .bind(response_status)
+    .bind(error)
+    .execute(&mut *db_tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE webhook_deliveries
+         SET attempts = attempts + 1,
+             status = $2,
+             next_attempt_at = NOW() + make_interval(secs => $3),
+             delivered_at = CASE WHEN $2 = 'delivered' THEN NOW() END
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(status)
+    .bind(retry_in_seconds as f64)
+    .execute(&mut *db_tx)
+    .await?;
+
+    db_tx.commit().await?;
+
+    Ok(())
+}
+
+/// The latest `limit` deliveries to subscription `id`, newest first, without
+/// their attempts.
+pub(crate) async fn get_deliveries(
+    pool: &PgPool,
+    id: i32,
+    limit: i64,
+) -> Result<Vec<Delivery>, sqlx::Error> {
+    let deliveries = sqlx::query_as::<_, Delivery>(
+        "SELECT d.id, o.transaction_id, o.event_type, d.status, d.created_at,
+                CASE WHEN d.status = 'pending' THEN d.next_attempt_at END AS next_attempt_at,
+                d.delivered_at
+         FROM webhook_deliveries d
+         JOIN webhook_outbox o ON o.id = d.outbox_id
+         WHERE d.subscription_id = $1
+         ORDER BY d.id DESC
+         LIMIT $2",
+    )
+    .bind(id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deliveries)
+}
+
+/// Every attempt at the deliveries `ids`, in order.
+pub(crate) async fn get_delivery_attempts(
+    pool: &PgPool,
+    ids: &[i64],
+) -> Result<Vec<DeliveryAttempt>, sqlx::Error> {
+    let attempts = sqlx::query_as::<_, DeliveryAttempt>(
+        "SELECT delivery_id, attempted_at, response_status, error
+         FROM webhook_delivery_attempts
+         WHERE delivery_id = ANY ($1)
+         ORDER BY id",
+    )
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attempts)
+}
@@ -0,0 +1,45 @@
+use crate::api::error::{ErrorCode, FieldError};
+use crate::modules::address;
+use crate::modules::webhooks::EventType;
+use serde::Deserialize;
+
+const MAX_URL_LENGTH: usize = 2048;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateWebhookRequest {
+    pub url: String,
+    pub address: String,
+    pub event_types: Vec<EventType>,
+}
+
+impl CreateWebhookRequest {
+    /// Validates the request, rewriting the address in canonical form.
+    pub fn validate(&mut self) -> Vec<FieldError> {
+        let mut result = vec![];
+
+        let scheme_ok = self.url.starts_with("https://") || self.url.starts_with("http://");
+        if !scheme_ok || self.url.len() > MAX_URL_LENGTH {
+            result.push(FieldError::new(
+                ErrorCode::InvalidUrl,
+                "url",
+                format!(
+                    "URL must be http or https and at most {} characters.",
+                    MAX_URL_LENGTH
+                ),
+            ));
+        }
+        match address::normalize(&self.address) {
+            Ok(address) => self.address = address,
+            Err(err) => result.push(err.for_field("address", "address")),
+        }
+        if self.event_types.is_empty() {
+            result.push(FieldError::new(
+                ErrorCode::InvalidEventTypes,
+                "event_types",
+                "At least one event type is required.",
+            ));
+        }
+
+        result
+    }
+}
@@ -0,0 +1,82 @@
+use crate::modules::holds::response::serialize_utc;
+use crate::modules::webhooks::{DeliveryStatus, EventType};
+use serde::{Serialize, Serializer};
+use sqlx::FromRow;
+use time::PrimitiveDateTime;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WebhookSubscription {
+    pub id: i32,
+    pub url: String,
+    pub address: String,
+    pub event_types: Vec<EventType>,
+    #[serde(serialize_with = "serialize_utc")]
+    pub created_at: PrimitiveDateTime,
+}
+
+/// Response to creating a subscription: the only time `secret` is returned.
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscription,
+    pub secret: String,
+}
+
+/// One event on its way to a subscription, with every attempt made so far.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Delivery {
+    pub id: i64,
+    pub transaction_id: i32,
+    pub event_type: EventType,
+    pub status: DeliveryStatus,
+    #[serde(serialize_with = "serialize_utc")]
+    pub created_at: PrimitiveDateTime,
+    /// When the next attempt is due, while the delivery is pending.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_utc"
+    )]
+    pub next_attempt_at: Option<PrimitiveDateTime>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_utc"
+    )]
+    pub delivered_at: Option<PrimitiveDateTime>,
+    #[sqlx(skip)]
+    pub attempts: Vec<DeliveryAttempt>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DeliveryAttempt {
+    #[serde(skip)]
+    pub delivery_id: i64,
+    #[serde(serialize_with = "serialize_utc")]
+    pub attempted_at: PrimitiveDateTime,
+    /// HTTP status of the response, when one was received.
+    pub response_status: Option<i16>,
+    pub error: Option<String>,
+}
+
+/// A pending delivery claimed by the dispatcher, with what it needs to send it.
+#[derive(Debug, FromRow)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+    pub event_type: EventType,
+    pub payload: String,
+}
+
+fn serialize_optional_utc<S>(
+    date: &Option<PrimitiveDateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => serialize_utc(date, serializer),
+        None => serializer.serialize_none(),
+    }
+}
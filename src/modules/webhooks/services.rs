@@ -0,0 +1,96 @@
+use crate::api::build_json_response;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::modules::webhooks::generate_secret;
+use crate::modules::webhooks::repository::{
+    get_deliveries, get_delivery_attempts, get_subscription, get_subscriptions,
+    insert_subscription, set_subscription_deleted,
+};
+use crate::modules::webhooks::request::CreateWebhookRequest;
+use crate::modules::webhooks::response::CreatedWebhook;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, delete, get, post, web};
+use sqlx::PgPool;
+
+/// How many of a subscription's latest deliveries are listed.
+const DELIVERIES_LIMIT: i64 = 100;
+
+#[get("")]
+async fn list_webhooks(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let subscriptions = get_subscriptions(&pool).await?;
+    Ok(build_json_response(subscriptions, StatusCode::OK))
+}
+
+/// Subscribes `url` to events of `address`. The response carries the secret
+/// deliveries are signed with; it cannot be retrieved again.
+#[post("")]
+async fn create_webhook(
+    pool: web::Data<PgPool>,
+    body: web::Json<CreateWebhookRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request = body.into_inner();
+
+    let errors = request.validate();
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let secret = generate_secret();
+    let subscription = insert_subscription(
+        &pool,
+        &request.url,
+        &request.address,
+        &request.event_types,
+        &secret,
+    )
+    .await?;
+
+    Ok(build_json_response(
+        CreatedWebhook {
+            subscription,
+            secret,
+        },
+        StatusCode::CREATED,
+    ))
+}
+
+#[delete("{id}")]
+async fn delete_webhook(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    if !set_subscription_deleted(&pool, path.into_inner()).await? {
+        return Err(webhook_not_found());
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The subscription's latest deliveries, newest first, each with its attempts.
+#[get("{id}/deliveries")]
+async fn list_deliveries(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    get_subscription(&pool, id)
+        .await?
+        .ok_or_else(webhook_not_found)?;
+
+    let mut deliveries = get_deliveries(&pool, id, DELIVERIES_LIMIT).await?;
+    let ids: Vec<i64> = deliveries.iter().map(|delivery| delivery.id).collect();
+    for attempt in get_delivery_attempts(&pool, &ids).await? {
+        if let Some(delivery) = deliveries
+            .iter_mut()
+            .find(|delivery| delivery.id == attempt.delivery_id)
+        {
+            delivery.attempts.push(attempt);
+        }
+    }
+
+    Ok(build_json_response(deliveries, StatusCode::OK))
+}
+
+fn webhook_not_found() -> ApiError {
+    ApiError::not_found(ErrorCode::WebhookNotFound, "Webhook not found.")
+}
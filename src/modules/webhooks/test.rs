@@ -0,0 +1,257 @@
+use crate::api::api_routes;
+use crate::configurations::Webhooks;
+use crate::modules::api_keys::Scope;
+use crate::modules::api_keys::services::create_api_key;
+use crate::modules::events::EventBus;
+use crate::modules::transactions::test::{
+    issuer, pg_repository, test_config, test_pool, unique_address,
+};
+use crate::modules::webhooks::dispatcher::{client, dispatch};
+use crate::modules::webhooks::repository::fan_out_outbox;
+use crate::modules::webhooks::{SIGNATURE_HEADER, sign_payload};
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, test, web};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+/// Headers and body of every request a receiver got.
+type Received = Arc<Mutex<Vec<(header::HeaderMap, String)>>>;
+
+fn bearer(secret: &str) -> (header::HeaderName, String) {
+    (header::AUTHORIZATION, format!("Bearer {}", secret))
+}
+
+/// Records the request, failing the first one.
+async fn receive(
+    request: HttpRequest,
+    body: String,
+    received: web::Data<Received>,
+) -> HttpResponse {
+    let mut received = received.lock().unwrap();
+    received.push((request.headers().clone(), body));
+
+    if received.len() == 1 {
+        HttpResponse::InternalServerError().finish()
+    } else {
+        HttpResponse::Ok().finish()
+    }
+}
+
+#[actix_web::test]
+async fn test_deliveries_are_signed_and_retried() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 39).await;
+    let address = unique_address(40);
+
+    let received: Received = Arc::default();
+    let data = web::Data::new(received.clone());
+    let receiver = HttpServer::new(move || {
+        App::new()
+            .app_data(data.clone())
+            .route("/hook", web::post().to(receive))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let url = format!("http://{}/hook", receiver.addrs()[0]);
+    let receiver = receiver.run();
+    let receiver_handle = receiver.handle();
+    actix_web::rt::spawn(receiver);
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/webhooks")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "url": url, "address": address, "event_types": ["deposit"] }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let webhook: serde_json::Value = test::read_body_json(response).await;
+    let id = webhook["id"].as_i64().unwrap();
+    let secret = webhook["secret"].as_str().unwrap().to_string();
+
+    let request = test::TestRequest::post()
+        .uri("/api/webhooks")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({ "url": "ftp://example.com", "address": address, "event_types": [] }))
+        .to_request();
+    let problem: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    let codes: Vec<&str> = problem["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes, ["INVALID_URL", "INVALID_EVENT_TYPES"]);
+
+    let request = test::TestRequest::post()
+        .uri("/api/transactions")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({
+            "address_from": funder,
+            "address_to": address,
+            "amount": "10",
+            "transaction_type": "Deposit",
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let transaction_id: serde_json::Value = test::read_body_json(response).await;
+
+    // Retries are due at once, and the receiver fails the first attempt.
+    let config = Webhooks {
+        backoff_base_seconds: 0,
+        ..Webhooks::default()
+    };
+    let client = client(&config).unwrap();
+    dispatch(&pool, &client, &config).await.unwrap();
+    dispatch(&pool, &client, &config).await.unwrap();
+
+    let requests: Vec<_> = received
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, body)| body.contains(&address))
+        .cloned()
+        .collect();
+    assert_eq!(requests.len(), 2);
+
+    let (headers, body) = &requests[1];
+    let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(payload["type"], "deposit");
+    assert_eq!(payload["transaction"]["id"], transaction_id);
+    assert_eq!(headers.get("X-Webhook-Event").unwrap(), "deposit");
+
+    let signature = headers.get(SIGNATURE_HEADER).unwrap().to_str().unwrap();
+    let timestamp: i64 = signature
+        .strip_prefix("t=")
+        .and_then(|rest| rest.split(',').next())
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(signature, sign_payload(&secret, timestamp, body));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/webhooks/{}/deliveries", id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let deliveries: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(deliveries.as_array().unwrap().len(), 1);
+    assert_eq!(deliveries[0]["status"], "delivered");
+    assert_eq!(deliveries[0]["transaction_id"], transaction_id);
+    let statuses: Vec<_> = deliveries[0]["attempts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|attempt| attempt["response_status"].as_i64().unwrap())
+        .collect();
+    assert_eq!(statuses, [500, 200]);
+
+    let request = test::TestRequest::delete()
+        .uri(&format!("/api/webhooks/{}", id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let request = test::TestRequest::delete()
+        .uri(&format!("/api/webhooks/{}", id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    receiver_handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_status_changes_are_delivered() {
+    let Some(pool) = test_pool().await else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    let funder = issuer(&pool, 60).await;
+    let address = unique_address(61);
+
+    let admin = create_api_key(&pool, "admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(test_config()))
+            .app_data(web::Data::new(EventBus::new()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(pg_repository(&pool))
+            .configure(api_routes),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/webhooks")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({
+            "url": "http://127.0.0.1:9/hook",
+            "address": address,
+            "event_types": ["deposit"],
+        }))
+        .to_request();
+    let webhook: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/transactions")
+        .insert_header(bearer(&admin.key))
+        .set_json(json!({
+            "address_from": funder,
+            "address_to": address,
+            "amount": "10",
+            "transaction_type": "Deposit",
+            "status": "pending",
+        }))
+        .to_request();
+    let transaction_id: i32 = test::call_and_read_body_json(&app, request).await;
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/transactions/{}/confirm", transaction_id))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let statuses: Vec<String> = sqlx::query_scalar(
+        "SELECT payload->'transaction'->>'status' FROM webhook_outbox
+         WHERE transaction_id = $1 ORDER BY id",
+    )
+    .bind(transaction_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(statuses, ["pending", "confirmed"]);
+
+    while fan_out_outbox(&pool, 100).await.unwrap() == 100 {}
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/webhooks/{}/deliveries", webhook["id"]))
+        .insert_header(bearer(&admin.key))
+        .to_request();
+    let deliveries: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(deliveries.as_array().unwrap().len(), 2);
+}